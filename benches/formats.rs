@@ -0,0 +1,131 @@
+//! Compares the read and write throughput of the store formats on a large cache store.
+//!
+//! Run with every format enabled:
+//!
+//! ```sh
+//! cargo bench --bench formats --features bincode,msgpack,cbor
+//! ```
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rusty_store::{Format, Storage, StoreHandle, Storing, StoringType};
+use serde::{Deserialize, Serialize};
+
+const RECORDS: u64 = 20_000;
+
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct Record {
+    pub id: u64,
+    pub name: String,
+    pub score: f64,
+    pub tags: Vec<String>,
+    pub parent: Option<u64>,
+}
+
+fn records() -> Vec<Record> {
+    (0..RECORDS)
+        .map(|id| Record {
+            id,
+            name: format!("record-{}", id),
+            score: id as f64 / 7.0,
+            tags: vec!["cached".to_owned(), format!("group-{}", id % 16)],
+            parent: id.checked_sub(1),
+        })
+        .collect()
+}
+
+macro_rules! cache_store {
+    ($name:ident, $format:expr) => {
+        #[derive(Serialize, Deserialize, Default)]
+        pub struct $name {
+            pub records: Vec<Record>,
+        }
+
+        impl Storing for $name {
+            fn store_type() -> StoringType {
+                StoringType::Cache
+            }
+
+            fn format() -> Format {
+                $format
+            }
+        }
+    };
+}
+
+cache_store!(RonCache, Format::Ron);
+#[cfg(feature = "bincode")]
+cache_store!(BincodeCache, Format::Bincode);
+#[cfg(feature = "msgpack")]
+cache_store!(MessagePackCache, Format::MessagePack);
+#[cfg(feature = "cbor")]
+cache_store!(CborCache, Format::Cbor);
+
+trait CacheStore: Storing {
+    fn with_records(records: Vec<Record>) -> Self;
+}
+
+macro_rules! impl_cache_store {
+    ($($name:ident),*) => {
+        $(impl CacheStore for $name {
+            fn with_records(records: Vec<Record>) -> Self {
+                Self { records }
+            }
+        })*
+    };
+}
+
+impl_cache_store!(RonCache);
+#[cfg(feature = "bincode")]
+impl_cache_store!(BincodeCache);
+#[cfg(feature = "msgpack")]
+impl_cache_store!(MessagePackCache);
+#[cfg(feature = "cbor")]
+impl_cache_store!(CborCache);
+
+fn bench_format<T: CacheStore>(c: &mut Criterion, name: &str, records: &[Record]) {
+    let dir = tempfile::tempdir().expect("Failed to create temporary directory");
+    let storage = Storage::from_dirs(
+        dir.path().join("cache"),
+        dir.path().join("data"),
+        dir.path().join("config"),
+    );
+
+    let mut handle = StoreHandle::<T>::new("cache");
+    *handle.get_store_mut() = T::with_records(records.to_vec());
+    storage.write(&mut handle).expect("Failed to write store");
+
+    let file = format!("cache/cache.{}", T::format().extension());
+    let size = std::fs::metadata(dir.path().join(file))
+        .expect("Failed to read store metadata")
+        .len();
+    println!("{}: {} bytes", name, size);
+
+    let mut group = c.benchmark_group("formats");
+    group.throughput(Throughput::Elements(RECORDS));
+    group.sample_size(20);
+
+    group.bench_function(BenchmarkId::new("write", name), |b| {
+        b.iter(|| storage.write(&mut handle).expect("Failed to write store"))
+    });
+    group.bench_function(BenchmarkId::new("read", name), |b| {
+        let mut handle = StoreHandle::<T>::new("cache");
+        b.iter(|| storage.read(&mut handle).expect("Failed to read store"))
+    });
+
+    group.finish();
+}
+
+fn formats(c: &mut Criterion) {
+    let records = records();
+
+    bench_format::<RonCache>(c, "ron", &records);
+    #[cfg(feature = "bincode")]
+    bench_format::<BincodeCache>(c, "bincode", &records);
+    #[cfg(feature = "msgpack")]
+    bench_format::<MessagePackCache>(c, "msgpack", &records);
+    #[cfg(feature = "cbor")]
+    bench_format::<CborCache>(c, "cbor", &records);
+}
+
+criterion_group!(benches, formats);
+criterion_main!(benches);
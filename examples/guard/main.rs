@@ -0,0 +1,32 @@
+use rusty_store::{Storage, StoreManager, Storing};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default, Storing)]
+pub struct MyStore {
+    pub count: u32,
+}
+
+fn main() {
+    // Initialize the Storage with the defaults
+    let storage = Storage::new("com.github.mazynoah.storage");
+
+    // Use `StoreManager` to manage the store.
+    let mut manager =
+        StoreManager::<MyStore>::new(&storage, "guard").expect("Failed to create StoreManager");
+
+    // Modify the data through a guard, which saves the changes when it goes out of scope.
+    {
+        let mut store = manager.edit();
+        store.count += 1;
+        store.count += 1;
+    }
+
+    // Commit explicitly to handle errors instead of only logging them.
+    let mut store = manager.edit();
+    store.count += 1;
+    store.commit().expect("Failed to save count to storage");
+
+    let counter = manager.get_store();
+
+    println!("Count: {}", counter.count);
+}
@@ -1,9 +1,9 @@
 extern crate proc_macro;
 use proc_macro::TokenStream;
 use quote::quote;
-use syn;
+use syn::{LitBool, LitInt, LitStr, Token};
 
-#[proc_macro_derive(Storing)]
+#[proc_macro_derive(Storing, attributes(storing))]
 pub fn storing_derive(input: TokenStream) -> TokenStream {
     let ast = syn::parse(input).unwrap();
     impl_storing(&ast)
@@ -11,8 +11,82 @@ pub fn storing_derive(input: TokenStream) -> TokenStream {
 
 fn impl_storing(ast: &syn::DeriveInput) -> TokenStream {
     let name = &ast.ident;
+    let options = match pretty_options(ast) {
+        Ok(options) => options,
+        Err(err) => return err.to_compile_error().into(),
+    };
+
+    if options.is_empty() {
+        return quote! {
+            impl Storing for #name {}
+        }
+        .into();
+    }
+
     let gen = quote! {
-        impl Storing for #name {}
+        impl Storing for #name {
+            fn pretty_config() -> ::rusty_store::PrettyConfig {
+                ::rusty_store::PrettyConfig::new().compact_arrays(true) #(#options)*
+            }
+        }
     };
     gen.into()
 }
+
+/// Collects the `PrettyConfig` builder calls requested by the `#[storing(...)]` attributes.
+fn pretty_options(ast: &syn::DeriveInput) -> syn::Result<Vec<proc_macro2::TokenStream>> {
+    let mut options = Vec::new();
+    let mut extensions = Vec::new();
+
+    for attr in ast
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("storing"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("indentor") {
+                let indentor: LitStr = meta.value()?.parse()?;
+                options.push(quote! { .indentor(::std::string::String::from(#indentor)) });
+            } else if meta.path.is_ident("struct_names") {
+                let enabled = flag(&meta)?;
+                options.push(quote! { .struct_names(#enabled) });
+            } else if meta.path.is_ident("compact_arrays") {
+                let enabled = flag(&meta)?;
+                options.push(quote! { .compact_arrays(#enabled) });
+            } else if meta.path.is_ident("depth_limit") {
+                let limit: LitInt = meta.value()?.parse()?;
+                options.push(quote! { .depth_limit(#limit) });
+            } else if meta.path.is_ident("implicit_some") {
+                if flag(&meta)? {
+                    extensions.push(quote! { ::rusty_store::Extensions::IMPLICIT_SOME });
+                }
+            } else if meta.path.is_ident("unwrap_newtypes") {
+                if flag(&meta)? {
+                    extensions.push(quote! { ::rusty_store::Extensions::UNWRAP_NEWTYPES });
+                }
+            } else if meta.path.is_ident("unwrap_variant_newtypes") {
+                if flag(&meta)? {
+                    extensions.push(quote! { ::rusty_store::Extensions::UNWRAP_VARIANT_NEWTYPES });
+                }
+            } else {
+                return Err(meta.error("unsupported storing option"));
+            }
+            Ok(())
+        })?;
+    }
+
+    if !extensions.is_empty() {
+        options.push(quote! { .extensions(#(#extensions)|*) });
+    }
+    Ok(options)
+}
+
+/// Parses an option which is either set by its name alone or with `= true` / `= false`.
+fn flag(meta: &syn::meta::ParseNestedMeta) -> syn::Result<bool> {
+    if meta.input.peek(Token![=]) {
+        let value: LitBool = meta.value()?.parse()?;
+        Ok(value.value)
+    } else {
+        Ok(true)
+    }
+}
@@ -0,0 +1,230 @@
+use std::panic;
+use std::sync::Arc;
+
+use log::{debug, info};
+use tokio::sync::{OwnedRwLockReadGuard, RwLock};
+use tokio::task;
+
+use crate::lock::LockMode;
+use crate::manager::{ModifyError, StoreManager};
+use crate::storage::{Storage, StoreError, StoreHandle, Storing};
+
+/// Read access to the store of an `AsyncStoreManager`, returned by [`AsyncStoreManager::read`].
+///
+/// Modifications wait until every `AsyncStoreRef` is dropped.
+pub type AsyncStoreRef<T> = OwnedRwLockReadGuard<StoreManager<T>, T>;
+
+impl Storage {
+    /// Reads the store like [`Storage::read`], without blocking the async runtime.
+    ///
+    /// The file is read and locked on tokio's blocking thread pool. The handle is only updated
+    /// once the store has been read, so it is left untouched if the future is dropped early.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rusty_store::{Storage, StoreHandle, Storing};
+    /// # use serde::{Deserialize, Serialize};
+    /// # #[derive(Serialize, Deserialize, Default, Storing)]
+    /// # pub struct MyStore {
+    /// #     pub count: u32,
+    /// # }
+    /// # async fn run() {
+    /// let storage = Storage::new("APP_ID");
+    /// let mut handle: StoreHandle<MyStore> = StoreHandle::new("my_store_id");
+    ///
+    /// storage.read_async(&mut handle).await.expect("Failed to read store");
+    /// # }
+    /// ```
+    pub async fn read_async<T>(&self, handle: &mut StoreHandle<T>) -> Result<(), StoreError>
+    where
+        T: Storing + Send + 'static,
+    {
+        let storage = self.clone();
+        let mut read = StoreHandle::<T>::new(handle.store_id());
+
+        *handle = blocking(move || storage.read(&mut read).map(|()| read)).await?;
+        Ok(())
+    }
+
+    /// Writes the store like [`Storage::write`], without blocking the async runtime.
+    ///
+    /// The store is serialized right away, then locked and written atomically on tokio's
+    /// blocking thread pool.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rusty_store::{Storage, StoreHandle, Storing};
+    /// # use serde::{Deserialize, Serialize};
+    /// # #[derive(Serialize, Deserialize, Default, Storing)]
+    /// # pub struct MyStore {
+    /// #     pub count: u32,
+    /// # }
+    /// # async fn run() {
+    /// let storage = Storage::new("APP_ID");
+    /// let mut handle: StoreHandle<MyStore> = StoreHandle::new("my_store_id");
+    ///
+    /// storage.write_async(&mut handle).await.expect("Failed to write store");
+    /// # }
+    /// ```
+    pub async fn write_async<T>(&self, handle: &mut StoreHandle<T>) -> Result<(), StoreError>
+    where
+        T: Storing + 'static,
+    {
+        debug!("Writing store with id: {}", handle.store_id());
+
+        let contents = self.serialize(handle.get_store())?;
+        let storage = self.clone();
+        let store_id = handle.store_id().to_owned();
+
+        let fingerprint = blocking(move || {
+            let _lock = storage.lock::<T>(&store_id, LockMode::Exclusive)?;
+            storage.write_contents::<T>(&store_id, &contents)
+        })
+        .await?;
+        handle.set_fingerprint(fingerprint);
+
+        info!("Successfully wrote store with id: {}", handle.store_id());
+        Ok(())
+    }
+}
+
+/// A `StoreManager` for async code.
+///
+/// Every operation touching the file runs on tokio's blocking thread pool, while waiting for
+/// other tasks using the manager does not block the runtime. Cloning an `AsyncStoreManager` is
+/// cheap and every clone manages the same store. An operation keeps running to completion if
+/// its future is dropped.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rusty_store::{AsyncStoreManager, Storage, Storing};
+/// # use serde::{Deserialize, Serialize};
+/// # #[derive(Serialize, Deserialize, Default, Storing)]
+/// # pub struct MyStore {
+/// #     pub count: u32,
+/// # }
+/// # async fn run() {
+/// let storage = Storage::new("APP_ID");
+/// let manager = AsyncStoreManager::<MyStore>::new(&storage, "my_store_id")
+///     .await
+///     .expect("Failed to create AsyncStoreManager");
+///
+/// manager
+///     .modify_store(|store| store.count += 1)
+///     .await
+///     .expect("Failed to write store modifications");
+///
+/// println!("Count: {}", manager.read().await.count);
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct AsyncStoreManager<T: Storing> {
+    manager: Arc<RwLock<StoreManager<T>>>,
+}
+
+impl<T> AsyncStoreManager<T>
+where
+    T: Storing + Send + Sync + 'static,
+{
+    /// Creates a new `AsyncStoreManager` by reading the store data from the provided `Storage`.
+    pub async fn new(storage: &Storage, store_id: &str) -> Result<Self, StoreError> {
+        let storage = storage.clone();
+        let store_id = store_id.to_owned();
+
+        let manager = blocking(move || StoreManager::new(&storage, &store_id)).await?;
+        Ok(Self::from(manager))
+    }
+
+    /// Returns read access to the store, waiting for any operation in progress.
+    pub async fn read(&self) -> AsyncStoreRef<T> {
+        let manager = Arc::clone(&self.manager).read_owned().await;
+        OwnedRwLockReadGuard::map(manager, StoreManager::get_store)
+    }
+
+    /// Reads the store from the storage and returns read access to it.
+    pub async fn get_store_alive(&self) -> Result<AsyncStoreRef<T>, StoreError> {
+        let mut manager = Arc::clone(&self.manager).write_owned().await;
+        let manager = blocking(move || {
+            manager.get_store_alive()?;
+            Ok::<_, StoreError>(manager)
+        })
+        .await?
+        .downgrade();
+        Ok(OwnedRwLockReadGuard::map(manager, StoreManager::get_store))
+    }
+
+    /// Modifies the store and commits it to the storage, like [`StoreManager::modify_store`].
+    pub async fn modify_store<F>(&self, change: F) -> Result<(), StoreError>
+    where
+        F: FnMut(&mut T) + Send + 'static,
+    {
+        self.with_manager(move |manager| manager.modify_store(change))
+            .await
+    }
+
+    /// Modifies the store with a fallible `change` and commits it to the storage, like
+    /// [`StoreManager::try_modify_store`].
+    pub async fn try_modify_store<F, R, E>(&self, change: F) -> Result<R, ModifyError<E>>
+    where
+        F: FnOnce(&mut T) -> Result<R, E> + Send + 'static,
+        R: Send + 'static,
+        E: Send + 'static,
+    {
+        self.with_manager(move |manager| manager.try_modify_store(change))
+            .await
+    }
+
+    /// Saves the store, like [`StoreManager::save`].
+    pub async fn save(&self) -> Result<(), StoreError> {
+        self.with_manager(StoreManager::save).await
+    }
+
+    /// Saves the store even if it was changed by another process, like
+    /// [`StoreManager::save_force`].
+    pub async fn save_force(&self) -> Result<(), StoreError> {
+        self.with_manager(StoreManager::save_force).await
+    }
+
+    /// Runs `operation` with exclusive access to the whole `StoreManager` API on tokio's
+    /// blocking thread pool.
+    pub async fn with_manager<F, R>(&self, operation: F) -> R
+    where
+        F: FnOnce(&mut StoreManager<T>) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let mut manager = Arc::clone(&self.manager).write_owned().await;
+        blocking(move || operation(&mut manager)).await
+    }
+}
+
+impl<T: Storing> Clone for AsyncStoreManager<T> {
+    /// Returns another handle to the same manager.
+    fn clone(&self) -> Self {
+        Self {
+            manager: Arc::clone(&self.manager),
+        }
+    }
+}
+
+impl<T: Storing> From<StoreManager<T>> for AsyncStoreManager<T> {
+    fn from(manager: StoreManager<T>) -> Self {
+        Self {
+            manager: Arc::new(RwLock::new(manager)),
+        }
+    }
+}
+
+/// Runs blocking file operations on tokio's blocking thread pool, resuming their panics.
+async fn blocking<F, R>(operation: F) -> R
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    match task::spawn_blocking(operation).await {
+        Ok(result) => result,
+        Err(err) => panic::resume_unwind(err.into_panic()),
+    }
+}
@@ -0,0 +1,274 @@
+use std::marker::PhantomData;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use log::{debug, error};
+
+use crate::conflict::Fingerprint;
+use crate::lock::LockMode;
+use crate::storage::{Storage, StoreError, Storing};
+
+/// Writes the modifications of a store from a background thread once they stopped coming in for
+/// a quiet period, coalescing bursts of modifications into a single write.
+///
+/// Pending modifications are written when the `Autosave` is dropped.
+#[derive(Debug)]
+pub(crate) struct Autosave<T: Storing> {
+    shared: Arc<Shared<T>>,
+    worker: Option<JoinHandle<()>>,
+}
+
+#[derive(Debug)]
+struct Shared<T: Storing> {
+    storage: Storage,
+    store_id: String,
+    delay: Duration,
+    state: Mutex<State>,
+    wake: Condvar,
+    /// Serializes the writes of the worker with the ones made on behalf of the manager.
+    writing: Mutex<()>,
+    _store: PhantomData<fn() -> T>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// The latest serialized store which has not been written yet.
+    pending: Option<Vec<u8>>,
+    changed_at: Option<Instant>,
+    /// Set when writing `pending` failed, so the worker waits for a new modification or a flush
+    /// instead of retrying in a loop.
+    stalled: bool,
+    /// The fingerprint of the store file as last read or written by the manager or the worker.
+    fingerprint: Option<Fingerprint>,
+    /// The error of the last failed background write, reported by the next flush.
+    error: Option<StoreError>,
+    shutdown: bool,
+    /// Set when the owner stops the worker while holding the store's exclusive lock, so the
+    /// final flush writes under that lock instead of waiting for it.
+    lock_held: bool,
+}
+
+impl<T: Storing + 'static> Autosave<T> {
+    pub(crate) fn start(
+        storage: Storage,
+        store_id: String,
+        fingerprint: Option<Fingerprint>,
+        delay: Duration,
+    ) -> Self {
+        let shared = Arc::new(Shared {
+            storage,
+            store_id,
+            delay,
+            state: Mutex::new(State {
+                fingerprint,
+                ..State::default()
+            }),
+            wake: Condvar::new(),
+            writing: Mutex::new(()),
+            _store: PhantomData,
+        });
+
+        let worker = {
+            let shared = shared.clone();
+            thread::Builder::new()
+                .name(format!("rusty-store-autosave-{}", shared.store_id))
+                .spawn(move || shared.run())
+                .expect("Failed to spawn autosave thread")
+        };
+
+        Self {
+            shared,
+            worker: Some(worker),
+        }
+    }
+}
+
+impl<T: Storing> Autosave<T> {
+    /// Replaces the pending modification with `contents` and restarts the quiet period.
+    pub(crate) fn schedule(&self, contents: Vec<u8>) {
+        let mut state = self.shared.state();
+        state.pending = Some(contents);
+        state.changed_at = Some(Instant::now());
+        state.stalled = false;
+        self.shared.wake.notify_all();
+    }
+
+    /// Writes the pending modification right away and returns the fingerprint of the store file.
+    ///
+    /// Fails with the error of the write, or with the error of the last failed background write
+    /// if there was nothing left to write. `locked` tells whether the caller already holds the
+    /// store's exclusive lock.
+    pub(crate) fn flush(&self, locked: bool) -> Result<Option<Fingerprint>, StoreError> {
+        self.shared.write_pending(locked)
+    }
+
+    /// Drops the pending modification, which is about to be superseded by a direct write, and
+    /// returns the fingerprint of the store file.
+    pub(crate) fn discard(&self) -> Option<Fingerprint> {
+        let _writing = self.shared.writing();
+        let mut state = self.shared.state();
+        state.pending = None;
+        state.error = None;
+        state.fingerprint
+    }
+
+    /// Returns whether a modification is waiting to be written.
+    pub(crate) fn is_pending(&self) -> bool {
+        self.shared.state().pending.is_some()
+    }
+
+    /// Returns the fingerprint of the store file as last read or written.
+    pub(crate) fn fingerprint(&self) -> Option<Fingerprint> {
+        self.shared.state().fingerprint
+    }
+
+    /// Records the fingerprint of a store file read or written by the manager.
+    pub(crate) fn set_fingerprint(&self, fingerprint: Option<Fingerprint>) {
+        self.shared.state().fingerprint = fingerprint;
+    }
+
+    /// Stops the worker after writing the pending modification, and returns the fingerprint of
+    /// the store file. `locked` tells whether the caller holds the store's exclusive lock, which
+    /// the worker then cannot take itself.
+    pub(crate) fn stop(self, locked: bool) -> Option<Fingerprint> {
+        self.shared.state().lock_held = locked;
+        let shared = self.shared.clone();
+        drop(self);
+        let fingerprint = shared.state().fingerprint;
+        fingerprint
+    }
+}
+
+impl<T: Storing> Drop for Autosave<T> {
+    fn drop(&mut self) {
+        self.shared.state().shutdown = true;
+        self.shared.wake.notify_all();
+
+        if let Some(worker) = self.worker.take() {
+            if worker.join().is_err() {
+                error!(
+                    "Autosave thread for store with id: {} panicked",
+                    self.shared.store_id
+                );
+            }
+        }
+    }
+}
+
+impl<T: Storing> Shared<T> {
+    fn run(&self) {
+        let mut state = self.state();
+        while !state.shutdown {
+            let changed_at = match state.changed_at {
+                Some(changed_at) if state.pending.is_some() && !state.stalled => changed_at,
+                _ => {
+                    state = self
+                        .wake
+                        .wait(state)
+                        .unwrap_or_else(PoisonError::into_inner);
+                    continue;
+                }
+            };
+
+            let elapsed = changed_at.elapsed();
+            if elapsed < self.delay {
+                state = self
+                    .wake
+                    .wait_timeout(state, self.delay - elapsed)
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .0;
+                continue;
+            }
+
+            drop(state);
+            // Waiting on the store lock while it is held by the thread stopping the worker
+            // would never end, so the lock is given up on shutdown and left to the final flush.
+            let result = self
+                .storage
+                .lock_unless::<T>(&self.store_id, LockMode::Exclusive, || {
+                    self.state().shutdown
+                })
+                .and_then(|lock| match lock {
+                    Some(_lock) => self.write_pending(true),
+                    None => Ok(None),
+                });
+            state = self.state();
+
+            if let Err(err) = result {
+                error!(
+                    "Failed to autosave store with id: {}, error: {:?}",
+                    self.store_id, err
+                );
+                state.error = Some(err);
+            }
+        }
+        let locked = state.lock_held;
+        drop(state);
+
+        debug!("Flushing autosave for store with id: {}", self.store_id);
+        if let Err(err) = self.write_pending(locked) {
+            error!(
+                "Failed to flush autosave for store with id: {}, error: {:?}",
+                self.store_id, err
+            );
+        }
+    }
+
+    fn write_pending(&self, locked: bool) -> Result<Option<Fingerprint>, StoreError> {
+        // The store lock is taken before `writing`, in the same order as a manager holding an
+        // exclusive lock, so neither can wait on the other.
+        let _lock = if locked {
+            None
+        } else {
+            Some(
+                self.storage
+                    .lock::<T>(&self.store_id, LockMode::Exclusive)?,
+            )
+        };
+        let _writing = self.writing();
+
+        let (contents, expected) = {
+            let mut state = self.state();
+            match state.pending.take() {
+                Some(contents) => (contents, state.fingerprint),
+                None => {
+                    return match state.error.take() {
+                        Some(err) => Err(err),
+                        None => Ok(state.fingerprint),
+                    }
+                }
+            }
+        };
+
+        debug!("Autosaving store with id: {}", self.store_id);
+        let result = self
+            .storage
+            .ensure_fingerprint::<T>(&self.store_id, expected)
+            .and_then(|()| self.storage.write_contents::<T>(&self.store_id, &contents));
+
+        let mut state = self.state();
+        match result {
+            Ok(fingerprint) => {
+                state.fingerprint = Some(fingerprint);
+                state.error = None;
+                Ok(Some(fingerprint))
+            }
+            Err(err) => {
+                if state.pending.is_none() {
+                    state.pending = Some(contents);
+                    state.stalled = true;
+                }
+                Err(err)
+            }
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn writing(&self) -> MutexGuard<'_, ()> {
+        self.writing.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
@@ -0,0 +1,223 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use log::{debug, info, warn};
+
+use crate::lock::LockMode;
+use crate::storage::{Storage, StoreError, StoreHandle, Storing};
+
+/// A previous version of a store, kept next to it when backups are enabled with
+/// [`Storage::with_backups`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Backup {
+    index: usize,
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl Backup {
+    /// Returns the position of the backup, `1` being the most recent one.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the path of the backup file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the time the backed up version was last written, if the platform provides it.
+    pub fn modified(&self) -> Option<SystemTime> {
+        self.modified
+    }
+}
+
+impl Storage {
+    /// Lists the backups of the store `store_id`, most recent first.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rusty_store::{Storage, Storing};
+    /// # use serde::{Deserialize, Serialize};
+    /// # #[derive(Serialize, Deserialize, Default, Storing)]
+    /// # pub struct MyStore {
+    /// #     pub count: u32,
+    /// # }
+    /// let storage = Storage::new("APP_ID").with_backups(3);
+    ///
+    /// for backup in storage.backups::<MyStore>("my_store_id").expect("Failed to list backups") {
+    ///     println!("{}: {:?}", backup.index(), backup.path());
+    /// }
+    /// ```
+    pub fn backups<T: Storing>(&self, store_id: &str) -> Result<Vec<Backup>, StoreError> {
+        Self::backups_of(&self.store_path::<T>(store_id))
+    }
+
+    /// Lists the backups of the store file at `path`, most recent first.
+    fn backups_of(path: &Path) -> Result<Vec<Backup>, StoreError> {
+        let (Some(dir), Some(file_name)) = (path.parent(), path.file_name()) else {
+            return Ok(Vec::new());
+        };
+        let prefix = format!("{}.", file_name.to_string_lossy());
+
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(StoreError::Read(err)),
+        };
+
+        let mut backups = Vec::new();
+        for entry in entries {
+            let entry = entry.map_err(StoreError::Read)?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+
+            let index = name
+                .strip_prefix(&prefix)
+                .and_then(|rest| rest.strip_suffix(".bak"))
+                .and_then(|index| index.parse::<usize>().ok());
+
+            if let Some(index) = index {
+                backups.push(Backup {
+                    index,
+                    path: entry.path(),
+                    modified: entry.metadata().and_then(|meta| meta.modified()).ok(),
+                });
+            }
+        }
+
+        backups.sort_by_key(Backup::index);
+        debug!("Found {} backups for path: {:?}", backups.len(), path);
+        Ok(backups)
+    }
+
+    /// Reads the store saved in `backup` without modifying the current store.
+    pub fn read_backup<T: Storing>(&self, backup: &Backup) -> Result<T, StoreError> {
+        debug!("Reading backup at path: {:?}", backup.path);
+        let contents = fs::read(&backup.path).map_err(StoreError::Read)?;
+        self.parse(&contents)
+    }
+
+    /// Loads `backup` into the handle and writes it as the current version of the store.
+    ///
+    /// The version being replaced is itself backed up, so a restore can be undone.
+    pub fn restore_backup<T: Storing>(
+        &self,
+        handle: &mut StoreHandle<T>,
+        backup: &Backup,
+    ) -> Result<(), StoreError> {
+        let _lock = self.lock::<T>(handle.store_id(), LockMode::Exclusive)?;
+
+        handle.set_store(self.read_backup(backup)?);
+        self.write_unlocked(handle)?;
+
+        info!(
+            "Restored store with id: {} from backup: {:?}",
+            handle.store_id(),
+            backup.path
+        );
+        Ok(())
+    }
+
+    /// Restores the most recent backup that can still be read, skipping broken ones, and
+    /// returns it.
+    ///
+    /// Fails with `StoreError::NoBackup` if no backup could be read.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rusty_store::{Storage, StoreHandle, Storing};
+    /// # use serde::{Deserialize, Serialize};
+    /// # #[derive(Serialize, Deserialize, Default, Storing)]
+    /// # pub struct MyStore {
+    /// #     pub count: u32,
+    /// # }
+    /// let storage = Storage::new("APP_ID").with_backups(3);
+    /// let mut handle = StoreHandle::<MyStore>::new("my_store_id");
+    ///
+    /// if let Ok(backup) = storage.restore_last_good(&mut handle) {
+    ///     println!("Restored {:?}", backup.path());
+    /// }
+    /// ```
+    pub fn restore_last_good<T: Storing>(
+        &self,
+        handle: &mut StoreHandle<T>,
+    ) -> Result<Backup, StoreError> {
+        let _lock = self.lock::<T>(handle.store_id(), LockMode::Exclusive)?;
+        self.restore_last_good_unlocked(handle)
+    }
+
+    /// Restores the most recent readable backup, assuming the caller already holds the lock.
+    pub(crate) fn restore_last_good_unlocked<T: Storing>(
+        &self,
+        handle: &mut StoreHandle<T>,
+    ) -> Result<Backup, StoreError> {
+        for backup in self.backups::<T>(handle.store_id())? {
+            match self.read_backup(&backup) {
+                Ok(store) => {
+                    handle.set_store(store);
+                    self.write_unlocked(handle)?;
+
+                    info!(
+                        "Restored store with id: {} from backup: {:?}",
+                        handle.store_id(),
+                        backup.path
+                    );
+                    return Ok(backup);
+                }
+                Err(err) => warn!("Skipping unreadable backup {:?}: {}", backup.path, err),
+            }
+        }
+
+        Err(StoreError::NoBackup(handle.store_id().to_owned()))
+    }
+
+    /// Shifts the existing backups of the file at `path` and turns the file into the most
+    /// recent backup, keeping at most [`Storage::with_backups`] versions. Backups beyond that
+    /// count, left by an earlier configuration, are removed.
+    pub(crate) fn rotate_backups(&self, path: &Path) -> Result<(), StoreError> {
+        let count = self.backup_count();
+        if !path.exists() {
+            return Ok(());
+        }
+
+        for backup in Self::backups_of(path)? {
+            if backup.index > count {
+                debug!("Removing backup beyond the kept count: {:?}", backup.path);
+                fs::remove_file(&backup.path).map_err(StoreError::Backup)?;
+            }
+        }
+        if count == 0 {
+            return Ok(());
+        }
+        debug!("Rotating {} backups for path: {:?}", count, path);
+
+        for index in (1..count).rev() {
+            let from = Self::backup_path(path, index);
+            if from.exists() {
+                fs::rename(&from, Self::backup_path(path, index + 1))
+                    .map_err(StoreError::Backup)?;
+            }
+        }
+
+        let latest = Self::backup_path(path, 1);
+        let _ = fs::remove_file(&latest);
+
+        // The store file is about to be replaced by a rename, so a hard link keeps its previous
+        // contents without copying them. Fall back to a copy where links are not supported.
+        if fs::hard_link(path, &latest).is_err() {
+            fs::copy(path, &latest).map_err(StoreError::Backup)?;
+        }
+
+        Ok(())
+    }
+
+    fn backup_path(path: &Path, index: usize) -> PathBuf {
+        let mut name = path.file_name().unwrap_or_default().to_os_string();
+        name.push(format!(".{}.bak", index));
+        path.with_file_name(name)
+    }
+}
@@ -0,0 +1,126 @@
+use std::borrow::Cow;
+#[cfg(not(all(feature = "zstd", feature = "gzip")))]
+use std::io;
+#[cfg(feature = "gzip")]
+use std::io::{Read, Write};
+
+use log::debug;
+
+use crate::format::Format;
+use crate::storage::StoreError;
+
+/// Starts every zstd frame.
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xB5, 0x2F, 0xFD];
+/// Starts every gzip member.
+const GZIP_MAGIC: &[u8] = &[0x1F, 0x8B];
+
+/// The compression applied to a store file after it is serialized, chosen per store with
+/// [`Storing::compression`] or for every store with [`Storage::with_compression`].
+///
+/// Compressed files are plain zstd or gzip streams, which `zstd -d` or `gunzip` can open, and are
+/// recognized by their magic bytes when read, whatever the configured compression, so enabling
+/// or changing the compression keeps existing stores readable. Each algorithm is enabled by the
+/// cargo feature of the same name.
+///
+/// The magic bytes are not valid UTF-8, so they never start the file of a text format. Binary
+/// formats may start with them: their files are read as they are when they do not decompress.
+///
+/// [`Storing::compression`]: crate::Storing::compression
+/// [`Storage::with_compression`]: crate::Storage::with_compression
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Compression {
+    /// Stores the serialized contents as is.
+    #[default]
+    None,
+    /// Zstandard, fast and with a good ratio.
+    #[cfg(feature = "zstd")]
+    Zstd,
+    /// Gzip, slower but readable by common tools.
+    #[cfg(feature = "gzip")]
+    Gzip,
+}
+
+impl Compression {
+    /// Compresses serialized store contents.
+    pub(crate) fn compress(self, contents: Vec<u8>) -> Result<Vec<u8>, StoreError> {
+        match self {
+            Compression::None => Ok(contents),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => {
+                zstd::stream::encode_all(contents.as_slice(), 0).map_err(StoreError::Compression)
+            }
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder
+                    .write_all(&contents)
+                    .and_then(|()| encoder.finish())
+                    .map_err(StoreError::Compression)
+            }
+        }
+    }
+
+    /// Decompresses the contents of a store file in `format` if they start with the magic bytes
+    /// of a compression algorithm, and returns them unchanged otherwise.
+    pub(crate) fn decompress(contents: &[u8], format: Format) -> Result<Cow<'_, [u8]>, StoreError> {
+        let decompressed = if contents.starts_with(ZSTD_MAGIC) {
+            Self::unzstd(contents)
+        } else if contents.starts_with(GZIP_MAGIC) {
+            Self::gunzip(contents)
+        } else {
+            return Ok(Cow::Borrowed(contents));
+        };
+
+        match decompressed {
+            Ok(decompressed) => Ok(Cow::Owned(decompressed)),
+            // Binary stores may start with the magic bytes, they are then left to their format.
+            Err(err) if !format.is_text() => {
+                debug!(
+                    "Reading store starting with compression magic bytes as is, error: {:?}",
+                    err
+                );
+                Ok(Cow::Borrowed(contents))
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    #[cfg(feature = "zstd")]
+    fn unzstd(compressed: &[u8]) -> Result<Vec<u8>, StoreError> {
+        zstd::stream::decode_all(compressed).map_err(StoreError::Decompression)
+    }
+
+    #[cfg(not(feature = "zstd"))]
+    fn unzstd(_compressed: &[u8]) -> Result<Vec<u8>, StoreError> {
+        Err(Self::unsupported("zstd"))
+    }
+
+    #[cfg(feature = "gzip")]
+    fn gunzip(compressed: &[u8]) -> Result<Vec<u8>, StoreError> {
+        let mut decompressed = Vec::new();
+        flate2::read::GzDecoder::new(compressed)
+            .read_to_end(&mut decompressed)
+            .map(|_| decompressed)
+            .map_err(StoreError::Decompression)
+    }
+
+    #[cfg(not(feature = "gzip"))]
+    fn gunzip(_compressed: &[u8]) -> Result<Vec<u8>, StoreError> {
+        Err(Self::unsupported("gzip"))
+    }
+
+    /// The error for a file compressed with an algorithm whose feature is disabled. It is not a
+    /// parse error, so the file is never treated as corrupt.
+    #[cfg(not(all(feature = "zstd", feature = "gzip")))]
+    fn unsupported(feature: &str) -> StoreError {
+        StoreError::Read(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!(
+                "store file is compressed with {}, enable the `{}` feature to read it",
+                feature, feature
+            ),
+        ))
+    }
+}
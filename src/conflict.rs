@@ -0,0 +1,116 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io;
+
+use log::{debug, warn};
+
+use crate::lock::LockMode;
+use crate::storage::{Storage, StoreError, StoreHandle, Storing};
+
+/// Identifies the contents of a store file at the time a handle last read or wrote it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Fingerprint {
+    len: u64,
+    hash: u64,
+}
+
+impl Fingerprint {
+    pub(crate) fn of(contents: &[u8]) -> Self {
+        let mut hasher = DefaultHasher::new();
+        contents.hash(&mut hasher);
+
+        Self {
+            len: contents.len() as u64,
+            hash: hasher.finish(),
+        }
+    }
+}
+
+impl Storage {
+    /// Writes the store like [`Storage::write`], unless the store file changed since the handle
+    /// last read or wrote it. In that case nothing is written and `StoreError::Conflict` is
+    /// returned, so changes made by hand or by another process are not silently overwritten.
+    ///
+    /// Handles that never read or wrote their store are written unconditionally.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rusty_store::{Storage, StoreHandle, Storing};
+    /// # use serde::{Deserialize, Serialize};
+    /// # #[derive(Serialize, Deserialize, Default, Storing)]
+    /// # pub struct MyStore {
+    /// #     pub count: u32,
+    /// # }
+    /// let storage = Storage::new("APP_ID");
+    /// let mut handle: StoreHandle<MyStore> = StoreHandle::new("my_store_id");
+    ///
+    /// storage.read(&mut handle).expect("Failed to read store");
+    /// handle.get_store_mut().count += 1;
+    /// storage.write_if_unchanged(&mut handle).expect("Failed to write store");
+    /// ```
+    pub fn write_if_unchanged<T: Storing>(
+        &self,
+        handle: &mut StoreHandle<T>,
+    ) -> Result<(), StoreError> {
+        let _lock = self.lock::<T>(handle.store_id(), LockMode::Exclusive)?;
+        self.write_if_unchanged_unlocked(handle)
+    }
+
+    /// Writes the store like [`Storage::write_if_unchanged`], assuming the caller already holds
+    /// its lock.
+    pub(crate) fn write_if_unchanged_unlocked<T: Storing>(
+        &self,
+        handle: &mut StoreHandle<T>,
+    ) -> Result<(), StoreError> {
+        self.ensure_unchanged(handle)?;
+        self.write_unlocked(handle)
+    }
+
+    /// Fails with `StoreError::Conflict` if the store file no longer matches the fingerprint the
+    /// handle recorded when it last read or wrote it.
+    pub(crate) fn ensure_unchanged<T: Storing>(
+        &self,
+        handle: &StoreHandle<T>,
+    ) -> Result<(), StoreError> {
+        self.ensure_fingerprint::<T>(handle.store_id(), handle.fingerprint())
+    }
+
+    /// Fails with `StoreError::Conflict` if the file of the store `store_id` no longer matches
+    /// `expected`. Passes if there is no expectation.
+    pub(crate) fn ensure_fingerprint<T: Storing>(
+        &self,
+        store_id: &str,
+        expected: Option<Fingerprint>,
+    ) -> Result<(), StoreError> {
+        let Some(expected) = expected else {
+            return Ok(());
+        };
+
+        let path = self.store_path::<T>(store_id);
+        let current = match fs::read(&path) {
+            Ok(contents) => Some(Fingerprint::of(&contents)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => return Err(StoreError::Read(err)),
+        };
+
+        if current == Some(expected) {
+            debug!("Store with id: {} is unchanged on disk", store_id);
+            Ok(())
+        } else {
+            warn!(
+                "Store with id: {} was modified externally since it was last read",
+                store_id
+            );
+            Err(StoreError::Conflict(store_id.to_owned()))
+        }
+    }
+
+    /// Reads the version of the store currently on disk without touching the handle.
+    pub(crate) fn read_current<T: Storing>(&self, store_id: &str) -> Result<T, StoreError> {
+        let path = self.store_path::<T>(store_id);
+        let contents = fs::read(path).map_err(StoreError::Read)?;
+        self.parse(&contents)
+    }
+}
@@ -0,0 +1,255 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Nonce};
+use log::{debug, info};
+use sha2::{Digest, Sha256};
+
+use crate::storage::StoreError;
+
+/// The length in bytes of the keys returned by a [`KeyProvider`].
+pub const KEY_LEN: usize = 32;
+
+const MAGIC: &[u8] = b"RSENC\x01";
+const KEY_CHECK_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const SALT_LEN: usize = 16;
+
+/// Provides the keys that encrypt the stores whose [`Storing::encrypted`] returns `true`, set
+/// with [`Storage::with_key_provider`].
+///
+/// Encrypted files start with a header holding the salt returned by [`KeyProvider::salt`] when
+/// they were written, so a provider deriving its keys, such as [`Passphrase`], can derive the
+/// key of any file again.
+///
+/// [`Storing::encrypted`]: crate::Storing::encrypted
+/// [`Storage::with_key_provider`]: crate::Storage::with_key_provider
+pub trait KeyProvider: Send + Sync {
+    /// Returns the salt stored in the files written from now on, empty by default for providers
+    /// whose key does not depend on a salt.
+    fn salt(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    /// Returns the key of a file written with `salt`.
+    fn key(&self, salt: &[u8]) -> Result<[u8; KEY_LEN], StoreError>;
+}
+
+/// A random key kept in a file, created on first use.
+///
+/// Keep the key file apart from the stores, for example in a directory only the user can read,
+/// since anyone reading it can decrypt them.
+#[derive(Debug, Clone)]
+pub struct KeyFile {
+    path: PathBuf,
+}
+
+impl KeyFile {
+    /// Uses the key in the file at `path`, generating it if the file does not exist yet.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Returns the path of the key file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn generate(&self) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+        let mut key = [0; KEY_LEN];
+        OsRng.fill_bytes(&mut key);
+        let mut file = options.open(&self.path)?;
+        file.write_all(&key)?;
+        file.sync_all()
+    }
+}
+
+impl KeyProvider for KeyFile {
+    fn key(&self, _salt: &[u8]) -> Result<[u8; KEY_LEN], StoreError> {
+        let key = match fs::read(&self.path) {
+            Ok(key) => key,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                info!("Generating key file at path: {:?}", self.path);
+                match self.generate() {
+                    Ok(()) => {}
+                    // Another process generated it in the meantime.
+                    Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {}
+                    Err(err) => return Err(StoreError::Key(err)),
+                }
+                fs::read(&self.path).map_err(StoreError::Key)?
+            }
+            Err(err) => return Err(StoreError::Key(err)),
+        };
+
+        key.try_into().map_err(|_| {
+            StoreError::Key(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("key file must hold exactly {} bytes", KEY_LEN),
+            ))
+        })
+    }
+}
+
+/// A key derived from a passphrase with Argon2.
+///
+/// Each `Passphrase` writes files with its own random salt. Derived keys are cached, so the
+/// derivation only runs once per salt.
+pub struct Passphrase {
+    passphrase: String,
+    salt: [u8; SALT_LEN],
+    keys: Mutex<HashMap<Vec<u8>, [u8; KEY_LEN]>>,
+}
+
+impl Passphrase {
+    /// Derives keys from `passphrase`.
+    pub fn new(passphrase: impl Into<String>) -> Self {
+        let mut salt = [0; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        Self {
+            passphrase: passphrase.into(),
+            salt,
+            keys: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl Debug for Passphrase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Passphrase")
+    }
+}
+
+impl KeyProvider for Passphrase {
+    fn salt(&self) -> Vec<u8> {
+        self.salt.to_vec()
+    }
+
+    fn key(&self, salt: &[u8]) -> Result<[u8; KEY_LEN], StoreError> {
+        let mut keys = self.keys.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(key) = keys.get(salt) {
+            return Ok(*key);
+        }
+
+        debug!("Deriving key from passphrase");
+        let mut key = [0; KEY_LEN];
+        argon2::Argon2::default()
+            .hash_password_into(self.passphrase.as_bytes(), salt, &mut key)
+            .map_err(|err| {
+                StoreError::Key(io::Error::new(io::ErrorKind::InvalidInput, err.to_string()))
+            })?;
+        keys.insert(salt.to_vec(), key);
+        Ok(key)
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct KeyProviderRef(pub(crate) Arc<dyn KeyProvider>);
+
+impl Debug for KeyProviderRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("KeyProvider")
+    }
+}
+
+impl KeyProviderRef {
+    /// Encrypts the contents of a store file.
+    ///
+    /// The file is laid out as the magic bytes, the salt length and salt, a check value of the
+    /// key, the nonce and the ciphertext. Everything before the nonce is authenticated along with
+    /// the ciphertext.
+    pub(crate) fn encrypt(&self, contents: &[u8]) -> Result<Vec<u8>, StoreError> {
+        let salt = self.0.salt();
+        let salt_len = u8::try_from(salt.len()).map_err(|_| {
+            StoreError::Key(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "salt must be at most 255 bytes long",
+            ))
+        })?;
+        let key = self.0.key(&salt)?;
+
+        let mut encrypted = MAGIC.to_vec();
+        encrypted.push(salt_len);
+        encrypted.extend_from_slice(&salt);
+        encrypted.extend_from_slice(&key_check(&key));
+
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = ChaCha20Poly1305::new(&key.into())
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: contents,
+                    aad: &encrypted,
+                },
+            )
+            .map_err(|_| StoreError::Encryption)?;
+        encrypted.extend_from_slice(&nonce);
+        encrypted.extend_from_slice(&ciphertext);
+        Ok(encrypted)
+    }
+
+    /// Decrypts the contents of a store file if they start with the magic bytes of an encrypted
+    /// file, and returns them unchanged otherwise.
+    pub(crate) fn decrypt<'a>(
+        provider: Option<&Self>,
+        contents: &'a [u8],
+    ) -> Result<Cow<'a, [u8]>, StoreError> {
+        let Some(header) = contents.strip_prefix(MAGIC) else {
+            return Ok(Cow::Borrowed(contents));
+        };
+        let provider = provider.ok_or(StoreError::NoKeyProvider)?;
+
+        let (&salt_len, rest) = header.split_first().ok_or(StoreError::Tampered)?;
+        let salt_len = usize::from(salt_len);
+        if rest.len() < salt_len + KEY_CHECK_LEN + NONCE_LEN {
+            return Err(StoreError::Tampered);
+        }
+        let (salt, rest) = rest.split_at(salt_len);
+        let (check, rest) = rest.split_at(KEY_CHECK_LEN);
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+
+        let key = provider.0.key(salt)?;
+        if check != key_check(&key) {
+            return Err(StoreError::WrongKey);
+        }
+
+        let aad = &contents[..contents.len() - NONCE_LEN - ciphertext.len()];
+        ChaCha20Poly1305::new(&key.into())
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad,
+                },
+            )
+            .map(Cow::Owned)
+            .map_err(|_| StoreError::Tampered)
+    }
+}
+
+/// A value identifying `key` without revealing it, telling a wrong key apart from a tampered
+/// file.
+fn key_check(key: &[u8; KEY_LEN]) -> [u8; KEY_CHECK_LEN] {
+    let digest = Sha256::new()
+        .chain_update(b"rusty-store key check")
+        .chain_update(key)
+        .finalize();
+    let mut check = [0; KEY_CHECK_LEN];
+    check.copy_from_slice(&digest[..KEY_CHECK_LEN]);
+    check
+}
@@ -0,0 +1,157 @@
+use ron::ser::PrettyConfig;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::storage::StoreError;
+
+/// The serialization format of a store file, chosen per store with [`Storing::format`].
+///
+/// RON is always available, the other formats are enabled by the cargo feature of the same
+/// name. The binary formats are much faster and smaller than the text ones for large stores,
+/// such as caches, but cannot be edited by hand.
+///
+/// [`Storing::format`]: crate::Storing::format
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Format {
+    /// Rusty Object Notation, pretty printed as configured by [`Storing::pretty_config`].
+    ///
+    /// [`Storing::pretty_config`]: crate::Storing::pretty_config
+    #[default]
+    Ron,
+    /// JSON, pretty printed.
+    #[cfg(feature = "json")]
+    Json,
+    /// TOML, pretty printed. The store must serialize to a table, such as a struct.
+    #[cfg(feature = "toml")]
+    Toml,
+    /// YAML.
+    #[cfg(feature = "yaml")]
+    Yaml,
+    /// Bincode, a compact binary format. Fields are not named, so adding, removing or
+    /// reordering fields makes existing files unreadable.
+    #[cfg(feature = "bincode")]
+    Bincode,
+    /// MessagePack, a binary format keeping field names, enabled by the `msgpack` feature.
+    #[cfg(feature = "msgpack")]
+    MessagePack,
+    /// CBOR, a binary format keeping field names.
+    #[cfg(feature = "cbor")]
+    Cbor,
+}
+
+impl Format {
+    /// Every format enabled in this build.
+    pub(crate) const ALL: &'static [Format] = &[
+        Format::Ron,
+        #[cfg(feature = "json")]
+        Format::Json,
+        #[cfg(feature = "toml")]
+        Format::Toml,
+        #[cfg(feature = "yaml")]
+        Format::Yaml,
+        #[cfg(feature = "bincode")]
+        Format::Bincode,
+        #[cfg(feature = "msgpack")]
+        Format::MessagePack,
+        #[cfg(feature = "cbor")]
+        Format::Cbor,
+    ];
+
+    /// The extension of store files written in this format, such as `ron` for `<id>.ron`.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Ron => "ron",
+            #[cfg(feature = "json")]
+            Format::Json => "json",
+            #[cfg(feature = "toml")]
+            Format::Toml => "toml",
+            #[cfg(feature = "yaml")]
+            Format::Yaml => "yaml",
+            #[cfg(feature = "bincode")]
+            Format::Bincode => "bin",
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack => "msgpack",
+            #[cfg(feature = "cbor")]
+            Format::Cbor => "cbor",
+        }
+    }
+
+    /// Whether files in this format are text, which can be edited by hand.
+    pub(crate) fn is_text(self) -> bool {
+        match self {
+            Format::Ron => true,
+            #[cfg(feature = "json")]
+            Format::Json => true,
+            #[cfg(feature = "toml")]
+            Format::Toml => true,
+            #[cfg(feature = "yaml")]
+            Format::Yaml => true,
+            #[cfg(feature = "bincode")]
+            Format::Bincode => false,
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack => false,
+            #[cfg(feature = "cbor")]
+            Format::Cbor => false,
+        }
+    }
+
+    /// Serializes a store into the contents of a store file, pretty printed with `pretty_config`
+    /// in RON.
+    pub(crate) fn serialize<S: Serialize>(
+        self,
+        store: &S,
+        pretty_config: PrettyConfig,
+    ) -> Result<Vec<u8>, StoreError> {
+        match self {
+            Format::Ron => ron::ser::to_string_pretty(store, pretty_config)
+                .map(String::into_bytes)
+                .map_err(StoreError::Ron),
+            #[cfg(feature = "json")]
+            Format::Json => serde_json::to_vec_pretty(store).map_err(StoreError::Json),
+            #[cfg(feature = "toml")]
+            Format::Toml => toml::to_string_pretty(store)
+                .map(String::into_bytes)
+                .map_err(StoreError::Toml),
+            #[cfg(feature = "yaml")]
+            Format::Yaml => serde_yaml::to_string(store)
+                .map(String::into_bytes)
+                .map_err(StoreError::Yaml),
+            #[cfg(feature = "bincode")]
+            Format::Bincode => bincode::serialize(store).map_err(StoreError::Bincode),
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack => rmp_serde::to_vec_named(store).map_err(StoreError::MessagePack),
+            #[cfg(feature = "cbor")]
+            Format::Cbor => {
+                let mut contents = Vec::new();
+                ciborium::into_writer(store, &mut contents).map_err(StoreError::Cbor)?;
+                Ok(contents)
+            }
+        }
+    }
+
+    /// Deserializes a store from the contents of a store file.
+    pub(crate) fn parse<T: DeserializeOwned>(self, contents: &[u8]) -> Result<T, StoreError> {
+        match self {
+            Format::Ron => ron::de::from_bytes(contents).map_err(StoreError::RonParse),
+            #[cfg(feature = "json")]
+            Format::Json => serde_json::from_slice(contents).map_err(StoreError::JsonParse),
+            #[cfg(feature = "toml")]
+            Format::Toml => {
+                let contents = std::str::from_utf8(contents)
+                    .map_err(|err| StoreError::TomlParse(serde::de::Error::custom(err)))?;
+                toml::from_str(contents).map_err(StoreError::TomlParse)
+            }
+            #[cfg(feature = "yaml")]
+            Format::Yaml => serde_yaml::from_slice(contents).map_err(StoreError::YamlParse),
+            #[cfg(feature = "bincode")]
+            Format::Bincode => bincode::deserialize(contents).map_err(StoreError::BincodeParse),
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack => {
+                rmp_serde::from_slice(contents).map_err(StoreError::MessagePackParse)
+            }
+            #[cfg(feature = "cbor")]
+            Format::Cbor => ciborium::from_reader(contents).map_err(StoreError::CborParse),
+        }
+    }
+}
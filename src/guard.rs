@@ -0,0 +1,140 @@
+use std::ops::{Deref, DerefMut};
+
+use log::{debug, error, warn};
+
+use crate::manager::StoreManager;
+use crate::snapshot::Snapshot;
+use crate::storage::{StoreError, Storing};
+
+/// Mutable access to the store of a `StoreManager` that saves it when dropped, returned by
+/// [`StoreManager::edit`].
+///
+/// The guard only saves if the store was mutably dereferenced. With autosave enabled, the
+/// modification is handed to autosave instead of being written right away. Like
+/// [`StoreManager::modify_store`], the store is rolled back if it cannot be saved. Dropping the
+/// guard can only log a failed save, use [`StoreGuard::commit`] to handle the error instead.
+///
+/// If the thread panics while the guard is alive, the modification is discarded rather than
+/// saved half done.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rusty_store::{Storage, StoreManager, Storing};
+/// # use serde::{Deserialize, Serialize};
+/// # #[derive(Serialize, Deserialize, Default, Storing)]
+/// # pub struct MyStore {
+/// #     pub count: u32,
+/// # }
+/// let storage = Storage::new("APP_ID");
+/// let mut manager = StoreManager::<MyStore>::new(&storage, "my_store_id")
+///        .expect("Failed to create StoreManager");
+///
+/// {
+///     let mut store = manager.edit();
+///     store.count += 1;
+/// } // Saved here
+///
+/// let mut store = manager.edit();
+/// store.count += 1;
+/// store.commit().expect("Failed to save store");
+/// ```
+#[derive(Debug)]
+pub struct StoreGuard<'a, T: Storing> {
+    manager: &'a mut StoreManager<T>,
+    dirty: bool,
+    /// The store before it was first mutably dereferenced.
+    snapshot: Option<Snapshot>,
+}
+
+impl<'a, T: Storing> StoreGuard<'a, T> {
+    pub(crate) fn new(manager: &'a mut StoreManager<T>) -> Self {
+        Self {
+            manager,
+            dirty: false,
+            snapshot: None,
+        }
+    }
+
+    /// Returns whether the store was mutably accessed through this guard.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Saves the store if it was modified, consuming the guard.
+    pub fn commit(mut self) -> Result<(), StoreError> {
+        self.save()
+    }
+
+    fn save(&mut self) -> Result<(), StoreError> {
+        if !self.dirty {
+            return Ok(());
+        }
+
+        self.dirty = false;
+        self.manager
+            .commit()
+            .map_err(|err| match self.snapshot.take() {
+                Some(snapshot) => self.manager.rolled_back(&snapshot, err),
+                None => {
+                    warn!("Store edited through a guard cannot be rolled back");
+                    err
+                }
+            })
+    }
+
+    fn rollback(&mut self) {
+        let result = match self.snapshot.take() {
+            Some(snapshot) => self.manager.rollback(&snapshot),
+            None => {
+                warn!("Store edited through a guard cannot be rolled back");
+                Ok(())
+            }
+        };
+        if let Err(err) = result {
+            warn!(
+                "Failed to roll back store edited through a guard, error: {:?}",
+                err
+            );
+        }
+    }
+}
+
+impl<T: Storing> Deref for StoreGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.manager.get_store()
+    }
+}
+
+impl<T: Storing> DerefMut for StoreGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        if !self.dirty {
+            self.snapshot = Snapshot::take(self.manager.get_store())
+                .inspect_err(|err| warn!("Failed to snapshot store, error: {:?}", err))
+                .ok();
+        }
+        self.dirty = true;
+        self.manager.get_store_mut()
+    }
+}
+
+impl<T: Storing> Drop for StoreGuard<'_, T> {
+    fn drop(&mut self) {
+        if self.dirty && std::thread::panicking() {
+            debug!("Discarding store edited through a guard dropped by a panic");
+            self.dirty = false;
+            self.rollback();
+            return;
+        }
+
+        if self.dirty {
+            debug!("Saving store edited through a dropped guard");
+        }
+
+        if let Err(err) = self.save() {
+            error!("Failed to save store on drop, error: {:?}", err);
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use std::collections::VecDeque;
+
+use crate::snapshot::Snapshot;
+
+/// Where a committed version of a store comes from, which decides how it enters the history.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Origin {
+    /// A modification made through the manager.
+    Modification,
+    /// A previous version restored by undo.
+    Undo,
+    /// A version restored by redo.
+    Redo,
+    /// A version read from disk.
+    Reload,
+}
+
+/// The bounded undo and redo stacks of a `StoreManager`.
+#[derive(Debug)]
+pub(crate) struct History {
+    depth: usize,
+    undo: VecDeque<Snapshot>,
+    redo: Vec<Snapshot>,
+}
+
+impl History {
+    pub(crate) fn new(depth: usize) -> Self {
+        Self {
+            depth,
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+        }
+    }
+
+    /// Records that `previous` was replaced by a version with the given `origin`.
+    pub(crate) fn record(&mut self, previous: Snapshot, origin: Origin) {
+        match origin {
+            Origin::Modification => {
+                self.redo.clear();
+                self.push_undo(previous);
+            }
+            Origin::Undo => self.redo.push(previous),
+            Origin::Redo => self.push_undo(previous),
+            Origin::Reload => {}
+        }
+    }
+
+    pub(crate) fn pop(&mut self, origin: Origin) -> Option<Snapshot> {
+        match origin {
+            Origin::Undo => self.undo.pop_back(),
+            Origin::Redo => self.redo.pop(),
+            Origin::Modification | Origin::Reload => None,
+        }
+    }
+
+    /// Puts back a version taken with [`History::pop`] which could not be restored.
+    pub(crate) fn unpop(&mut self, snapshot: Snapshot, origin: Origin) {
+        match origin {
+            Origin::Undo => self.undo.push_back(snapshot),
+            Origin::Redo => self.redo.push(snapshot),
+            Origin::Modification | Origin::Reload => {}
+        }
+    }
+
+    pub(crate) fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub(crate) fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    fn push_undo(&mut self, snapshot: Snapshot) {
+        if self.depth == 0 {
+            return;
+        }
+        if self.undo.len() == self.depth {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(snapshot);
+    }
+}
@@ -0,0 +1,109 @@
+use std::fs;
+use std::path::PathBuf;
+
+use log::{debug, info, warn};
+
+use crate::format::Format;
+use crate::migration;
+use crate::recovery::RecoveryPolicy;
+use crate::storage::{Storage, StoreError, Storing};
+
+impl Storage {
+    /// Moves a file of the store `store_id` left under a previous name to its current path,
+    /// converting it to the format of the store if needed. Does nothing if the store file
+    /// already exists.
+    ///
+    /// Files without extension were written before stores had one, in RON or in the format of
+    /// the store. Files with the extension of another format are converted from that format, and
+    /// are kept if they cannot be parsed. Reads then fail with the parse error, unless the store
+    /// has a recovery policy, in which case it starts from the default store.
+    pub(crate) fn migrate_legacy<T: Storing>(&self, store_id: &str) -> Result<(), StoreError> {
+        let path = self.store_path::<T>(store_id);
+        if path.exists() {
+            return Ok(());
+        }
+
+        let bare = self.dir_path::<T>().join(store_id);
+        if bare.is_file() {
+            return self.migrate_bare::<T>(store_id, bare, path);
+        }
+
+        for &format in Format::ALL.iter().filter(|&&format| format != T::format()) {
+            let legacy = self.format_path::<T>(store_id, format);
+            if !legacy.is_file() {
+                continue;
+            }
+
+            debug!(
+                "Converting store with id: {} from {:?} to {:?}",
+                store_id,
+                format,
+                T::format()
+            );
+            let contents = fs::read(&legacy).map_err(StoreError::Read)?;
+            let store: T = match self.decode::<T>(&contents, format).and_then(|contents| {
+                migration::parse(format, &contents, self.missing_fields::<T>())
+            }) {
+                Ok(parsed) => parsed.store,
+                // Stores recovering from broken files start from the default store instead.
+                Err(err)
+                    if err.is_parse() && self.recovery_policy::<T>() != RecoveryPolicy::Fail =>
+                {
+                    warn!(
+                        "Skipping unreadable store at path: {:?}, error: {:?}",
+                        legacy, err
+                    );
+                    continue;
+                }
+                Err(err) => {
+                    warn!(
+                        "Failed to convert store at path: {:?}, error: {:?}",
+                        legacy, err
+                    );
+                    return Err(err);
+                }
+            };
+            self.write_contents::<T>(store_id, &self.serialize(&store)?)?;
+            fs::remove_file(&legacy).map_err(StoreError::Write)?;
+
+            info!("Converted store at path: {:?} to path: {:?}", legacy, path);
+            return Ok(());
+        }
+
+        Ok(())
+    }
+
+    fn migrate_bare<T: Storing>(
+        &self,
+        store_id: &str,
+        bare: PathBuf,
+        path: PathBuf,
+    ) -> Result<(), StoreError> {
+        let contents = fs::read(&bare).map_err(StoreError::Read)?;
+
+        // A file which cannot be parsed in RON either is left to the recovery policy of the
+        // store once renamed.
+        let converted = match self.parse::<T>(&contents) {
+            Ok(_) => None,
+            Err(_) if T::format() != Format::Ron => self
+                .decode::<T>(&contents, Format::Ron)
+                .and_then(|contents| {
+                    migration::parse::<T>(Format::Ron, &contents, self.missing_fields::<T>())
+                })
+                .ok()
+                .map(|parsed| parsed.store),
+            Err(_) => None,
+        };
+
+        match converted {
+            Some(store) => {
+                self.write_contents::<T>(store_id, &self.serialize(&store)?)?;
+                fs::remove_file(&bare).map_err(StoreError::Write)?;
+            }
+            None => fs::rename(&bare, &path).map_err(StoreError::Write)?,
+        }
+
+        info!("Migrated store at path: {:?} to path: {:?}", bare, path);
+        Ok(())
+    }
+}
@@ -54,9 +54,49 @@
 //!
 
 extern crate rustystore_macros;
+pub use ron::extensions::Extensions;
+pub use ron::ser::PrettyConfig;
 pub use rustystore_macros::Storing;
+#[cfg(feature = "tokio")]
+mod async_io;
+mod autosave;
+mod backup;
+mod compression;
+mod conflict;
+#[cfg(feature = "encryption")]
+mod encryption;
+mod format;
+mod guard;
+mod history;
+mod legacy;
+mod lock;
 mod manager;
+mod migration;
+mod observer;
+mod patch;
+mod recovery;
+mod shared;
+mod snapshot;
 mod storage;
+mod syntax;
+mod value;
+#[cfg(feature = "watcher")]
+mod watcher;
 
-pub use manager::StoreManager;
+#[cfg(feature = "tokio")]
+pub use async_io::{AsyncStoreManager, AsyncStoreRef};
+pub use backup::Backup;
+pub use compression::Compression;
+#[cfg(feature = "encryption")]
+pub use encryption::{KeyFile, KeyProvider, Passphrase, KEY_LEN};
+pub use format::Format;
+pub use guard::StoreGuard;
+pub use manager::{LockedStoreManager, ModifyError, StoreManager};
+pub use migration::Migrations;
+pub use observer::Subscription;
+pub use recovery::{Recovery, RecoveryPolicy};
+pub use shared::{SharedStoreManager, SharedStoreRef};
 pub use storage::*;
+pub use value::Value;
+#[cfg(feature = "watcher")]
+pub use watcher::StoreWatcher;
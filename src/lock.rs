@@ -0,0 +1,132 @@
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::debug;
+
+use crate::storage::StoreError;
+
+/// How often a lock is retried while waiting for a timeout to expire.
+const RETRY_INTERVAL: Duration = Duration::from_millis(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LockMode {
+    Shared,
+    Exclusive,
+}
+
+/// An advisory lock on a store, held on a `<store_id>.lock` file in the store's directory.
+///
+/// The lock lives on a separate file because stores are replaced by renaming a new file over
+/// the old one, which would silently drop a lock held on the store file itself.
+/// The lock is released when the `StoreLock` is dropped.
+#[derive(Debug)]
+pub(crate) struct StoreLock {
+    file: File,
+    path: PathBuf,
+}
+
+impl StoreLock {
+    /// Acquires a lock on `path`, creating the file and its directory if needed.
+    ///
+    /// Waits at most `timeout` for other holders to release the lock, or indefinitely if `None`.
+    pub(crate) fn acquire(
+        path: PathBuf,
+        mode: LockMode,
+        timeout: Option<Duration>,
+    ) -> Result<Self, StoreError> {
+        let file = Self::open(&path)?;
+
+        debug!("Acquiring {:?} lock at path: {:?}", mode, path);
+        match timeout {
+            None => match mode {
+                LockMode::Shared => file.lock_shared(),
+                LockMode::Exclusive => file.lock(),
+            }
+            .map_err(StoreError::Lock)?,
+            Some(timeout) => {
+                Self::acquire_within(&file, &path, mode, Some(timeout), || false)?;
+            }
+        }
+        debug!("Acquired {:?} lock at path: {:?}", mode, path);
+
+        Ok(Self { file, path })
+    }
+
+    /// Acquires a lock on `path` like [`StoreLock::acquire`], but gives up and returns `None` as
+    /// soon as `cancelled` returns `true` while waiting.
+    pub(crate) fn acquire_unless(
+        path: PathBuf,
+        mode: LockMode,
+        timeout: Option<Duration>,
+        cancelled: impl Fn() -> bool,
+    ) -> Result<Option<Self>, StoreError> {
+        let file = Self::open(&path)?;
+
+        debug!("Acquiring {:?} lock at path: {:?}", mode, path);
+        if !Self::acquire_within(&file, &path, mode, timeout, cancelled)? {
+            debug!("Gave up acquiring {:?} lock at path: {:?}", mode, path);
+            return Ok(None);
+        }
+        debug!("Acquired {:?} lock at path: {:?}", mode, path);
+
+        Ok(Some(Self { file, path }))
+    }
+
+    fn open(path: &Path) -> Result<File, StoreError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(StoreError::CreateDir)?;
+        }
+
+        OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .map_err(StoreError::Lock)
+    }
+
+    /// Retries the lock until it is acquired, `timeout` expires or `cancelled` returns `true`.
+    ///
+    /// Returns whether the lock was acquired.
+    fn acquire_within(
+        file: &File,
+        path: &Path,
+        mode: LockMode,
+        timeout: Option<Duration>,
+        cancelled: impl Fn() -> bool,
+    ) -> Result<bool, StoreError> {
+        let started = Instant::now();
+        loop {
+            let attempt = match mode {
+                LockMode::Shared => file.try_lock_shared(),
+                LockMode::Exclusive => file.try_lock(),
+            };
+
+            match attempt {
+                Ok(()) => return Ok(true),
+                Err(TryLockError::Error(err)) => return Err(StoreError::Lock(err)),
+                Err(TryLockError::WouldBlock) if cancelled() => return Ok(false),
+                Err(TryLockError::WouldBlock) => match timeout {
+                    Some(timeout) if started.elapsed() >= timeout => {
+                        return Err(StoreError::Lock(io::Error::new(
+                            io::ErrorKind::TimedOut,
+                            format!("timed out after {:?} waiting for {:?}", timeout, path),
+                        )));
+                    }
+                    _ => thread::sleep(RETRY_INTERVAL),
+                },
+            }
+        }
+    }
+}
+
+impl Drop for StoreLock {
+    fn drop(&mut self) {
+        debug!("Releasing lock at path: {:?}", self.path);
+        let _ = self.file.unlock();
+    }
+}
@@ -1,3 +1,21 @@
+use std::fs;
+use std::io;
+use std::ops::{Deref, DerefMut};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use log::{debug, warn};
+use thiserror::Error;
+
+use crate::autosave::Autosave;
+use crate::backup::Backup;
+use crate::conflict::Fingerprint;
+use crate::guard::StoreGuard;
+use crate::history::{History, Origin};
+use crate::lock::{LockMode, StoreLock};
+use crate::observer::{Observers, Subscription};
+use crate::snapshot::Snapshot;
 use crate::storage::{Storage, StoreError, StoreHandle, Storing};
 
 /// `StoreManager` manages the lifecycle of a store within a specified `Storage` backend. It handles reading and writing store data, as well as providing mutable access to the store's contents.
@@ -8,9 +26,9 @@ use crate::storage::{Storage, StoreError, StoreHandle, Storing};
 ///
 /// ## Example
 ///
-/// ```rust
+/// ```rust,no_run
+/// use rusty_store::{Storage, StoreManager, Storing};
 /// use serde::{Deserialize, Serialize};
-/// use storage::{StoreManager, Storing};
 ///
 /// #[derive(Serialize, Deserialize, Default, Storing)]
 /// pub struct MyStore {
@@ -35,10 +53,17 @@ use crate::storage::{Storage, StoreError, StoreHandle, Storing};
 ///
 /// println!("Count: {}", counter.count);
 /// ```
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub struct StoreManager<T: Storing> {
     store: Storage,
     handle: StoreHandle<T>,
+    locked: bool,
+    autosave: Option<Autosave<T>>,
+    observers: Observers<T>,
+    history: Option<History>,
+    /// The store as last read or written, kept while observers or the history need the previous
+    /// version of the store.
+    committed: Option<Snapshot>,
 }
 
 impl<T: Storing> StoreManager<T> {
@@ -50,7 +75,13 @@ impl<T: Storing> StoreManager<T> {
     /// # Example
     ///
     /// ```
-    /// let storage = Storage::new(app_id);
+    /// # use rusty_store::{Storage, StoreHandle, StoreManager, Storing};
+    /// # use serde::{Deserialize, Serialize};
+    /// # #[derive(Serialize, Deserialize, Default, Storing)]
+    /// # pub struct MyStore {
+    /// #     pub count: u32,
+    /// # }
+    /// let storage = Storage::new("APP_ID");
     /// let handle = StoreHandle::<MyStore>::new("my_store_id");
     /// let manager = StoreManager::from_handle(&storage, handle).expect("Failed to create StoreManager");
     /// ```
@@ -60,6 +91,11 @@ impl<T: Storing> StoreManager<T> {
         Ok(Self {
             store: storage.clone(),
             handle,
+            locked: false,
+            autosave: None,
+            observers: Observers::default(),
+            history: None,
+            committed: None,
         })
     }
 
@@ -71,8 +107,14 @@ impl<T: Storing> StoreManager<T> {
     /// # Example
     ///
     /// ```
+    /// # use rusty_store::{Storage, StoreHandle, StoreManager, Storing};
+    /// # use serde::{Deserialize, Serialize};
+    /// # #[derive(Serialize, Deserialize, Default, Storing)]
+    /// # pub struct MyStore {
+    /// #     pub count: u32,
+    /// # }
     /// let storage = Storage::new("APP_ID");
-    /// let manager = StoreManager::new(&storage, "my_store_id").expect("Failed to create StoreManager");
+    /// let manager = StoreManager::<MyStore>::new(&storage, "my_store_id").expect("Failed to create StoreManager");
     /// ```
     pub fn new(storage: &Storage, store_id: &str) -> Result<Self, StoreError> {
         let mut handle = StoreHandle::<T>::new(store_id);
@@ -81,6 +123,11 @@ impl<T: Storing> StoreManager<T> {
         Ok(Self {
             store: storage.clone(),
             handle,
+            locked: false,
+            autosave: None,
+            observers: Observers::default(),
+            history: None,
+            committed: None,
         })
     }
 
@@ -97,40 +144,182 @@ impl<T: Storing> StoreManager<T> {
     /// Reads the stored data from the storage.
     /// This allows to get changes external to the application
     pub fn get_store_alive(&mut self) -> Result<&T, StoreError> {
-        self.store.read(&mut self.handle)?;
+        self.read()?;
         Ok(self.handle.get_store())
     }
 
-    /// Modifies the store and commits the changes to the storage
+    /// Reads the store from the storage if the file was changed since this manager last read or
+    /// wrote it, and returns whether the store was replaced.
+    ///
+    /// Unlike [`StoreManager::get_store_alive`], the file is only parsed when it changed, and
+    /// the current store is kept if it cannot be parsed. Modifications which were not committed
+    /// are replaced, while external changes are ignored as long as autosave has a modification
+    /// waiting to be written.
     ///
     /// # Example
     ///
+    /// ```no_run
+    /// # use rusty_store::{Storage, StoreHandle, StoreManager, Storing};
+    /// # use serde::{Deserialize, Serialize};
+    /// # #[derive(Serialize, Deserialize, Default, Storing)]
+    /// # pub struct MyStore {
+    /// #     pub count: u32,
+    /// # }
+    /// let storage = Storage::new("APP_ID");
+    /// let mut manager = StoreManager::<MyStore>::new(&storage, "my_store_id")
+    ///        .expect("Failed to create StoreManager");
+    ///
+    /// if manager.reload_if_changed().expect("Failed to reload store") {
+    ///     println!("Count: {}", manager.get_store().count);
+    /// }
     /// ```
+    pub fn reload_if_changed(&mut self) -> Result<bool, StoreError> {
+        let store_id = self.handle.store_id().to_owned();
+        {
+            let _lock = if self.locked {
+                None
+            } else {
+                Some(self.store.lock::<T>(&store_id, LockMode::Shared)?)
+            };
+
+            let known = match &self.autosave {
+                Some(autosave) if autosave.is_pending() => {
+                    debug!(
+                        "Ignoring external change to store with id: {} while autosave is pending",
+                        store_id
+                    );
+                    return Ok(false);
+                }
+                Some(autosave) => autosave.fingerprint(),
+                None => self.handle.fingerprint(),
+            };
+
+            let contents = match fs::read(self.store_path()) {
+                Ok(contents) => contents,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+                Err(err) => return Err(StoreError::Read(err)),
+            };
+            let fingerprint = Fingerprint::of(&contents);
+            if known == Some(fingerprint) {
+                return Ok(false);
+            }
+
+            self.handle.set_store(self.store.parse(&contents)?);
+            self.handle.set_fingerprint(fingerprint);
+            if let Some(autosave) = &self.autosave {
+                autosave.set_fingerprint(Some(fingerprint));
+            }
+        }
+
+        self.record_commit(Origin::Reload);
+        Ok(true)
+    }
+
+    /// Modifies the store and commits the changes to the storage
+    ///
+    /// The modification is transactional: if the store cannot be saved, the in-memory store is
+    /// rolled back to its state before `change` was applied, so it never diverges from the disk.
+    /// It is also rolled back if `change` panics, before the panic resumes. The rollback restores
+    /// a serialized snapshot, so fields skipped by serde are reset to their default.
+    /// If the snapshot cannot be restored either, [`StoreError::Rollback`] is returned.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rusty_store::{Storage, StoreHandle, StoreManager, Storing};
+    /// # use serde::{Deserialize, Serialize};
+    /// # #[derive(Serialize, Deserialize, Default, Storing)]
+    /// # pub struct MyStore {
+    /// #     pub count: u32,
+    /// # }
     /// let storage = Storage::new("APP_ID");
     /// let mut manager = StoreManager::<MyStore>::new(&storage, "my_store_id")
     ///        .expect("Failed to create StoreManager");
     ///
-    /// manager.modify_store(|store| store.some_field = 25).expect("Failed to write store modifications");
+    /// manager.modify_store(|store| store.count = 25).expect("Failed to write store modifications");
     /// ```
     pub fn modify_store<F>(&mut self, mut change: F) -> Result<(), StoreError>
     where
         F: FnMut(&mut T),
     {
-        let store = self.handle.get_store_mut();
-        change(store);
-        self.save()
+        let snapshot = Snapshot::take(self.handle.get_store())?;
+
+        self.apply(&snapshot, |store| change(store));
+
+        self.commit()
+            .map_err(|err| self.rolled_back(&snapshot, err))
     }
 
-    /// This method is used to modify the store without committing changes to disk.
+    /// Modifies the store with a fallible `change` and commits it to the storage.
+    ///
+    /// If `change` returns `Ok`, the store is saved and its value is returned. If it returns
+    /// `Err`, every mutation it made is discarded and nothing is written. Like
+    /// [`StoreManager::modify_store`], the store is also rolled back if it cannot be saved or if
+    /// `change` panics. If the mutations of a rejected `change` cannot be discarded, the error
+    /// of the rollback is returned instead of the rejection.
     ///
     /// # Example
     ///
+    /// ```no_run
+    /// # use rusty_store::{ModifyError, Storage, StoreHandle, StoreManager, Storing};
+    /// # use serde::{Deserialize, Serialize};
+    /// # #[derive(Serialize, Deserialize, Default, Storing)]
+    /// # pub struct MyStore {
+    /// #     pub count: u32,
+    /// # }
+    /// let storage = Storage::new("APP_ID");
+    /// let mut manager = StoreManager::<MyStore>::new(&storage, "my_store_id")
+    ///        .expect("Failed to create StoreManager");
+    ///
+    /// let result = manager.try_modify_store(|store| {
+    ///     store.count += 10;
+    ///     if store.count > 100 {
+    ///         return Err("count must not exceed 100");
+    ///     }
+    ///     Ok(store.count)
+    /// });
+    ///
+    /// match result {
+    ///     Ok(count) => println!("Count: {}", count),
+    ///     Err(ModifyError::Rejected(reason)) => println!("Rejected: {}", reason),
+    ///     Err(err) => println!("Failed to save: {}", err),
+    /// }
     /// ```
+    pub fn try_modify_store<F, R, E>(&mut self, change: F) -> Result<R, ModifyError<E>>
+    where
+        F: FnOnce(&mut T) -> Result<R, E>,
+    {
+        let snapshot = Snapshot::take(self.handle.get_store())?;
+
+        match self.apply(&snapshot, change) {
+            Ok(value) => {
+                self.commit()
+                    .map_err(|err| self.rolled_back(&snapshot, err))?;
+                Ok(value)
+            }
+            Err(err) => {
+                self.rollback(&snapshot)?;
+                Err(ModifyError::Rejected(err))
+            }
+        }
+    }
+
+    /// This method is used to modify the store without committing changes to disk.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rusty_store::{Storage, StoreHandle, StoreManager, Storing};
+    /// # use serde::{Deserialize, Serialize};
+    /// # #[derive(Serialize, Deserialize, Default, Storing)]
+    /// # pub struct MyStore {
+    /// #     pub count: u32,
+    /// # }
     /// let storage = Storage::new("APP_ID");
     /// let mut manager = StoreManager::<MyStore>::new(&storage, "my_store_id")
     ///        .expect("Failed to create StoreManager");
     ///
-    /// manager.modify_store_uncommitted(|store| store.some_field = 25);
+    /// manager.modify_store_uncommitted(|store| store.count = 25);
     ///
     /// manager.save().expect("Failed to save modifications");
     /// ```
@@ -142,8 +331,610 @@ impl<T: Storing> StoreManager<T> {
         change(store);
     }
 
+    /// Returns a guard giving mutable access to the store, which saves the store when dropped
+    /// if it was modified. See [`StoreGuard`].
+    pub fn edit(&mut self) -> StoreGuard<'_, T> {
+        StoreGuard::new(self)
+    }
+
     /// This method writes the current state of the store to the storage.
+    ///
+    /// Fails with `StoreError::Conflict` without writing anything if the store file was modified
+    /// since the manager last read or wrote it, for example by hand or by another process.
+    /// Use [`StoreManager::save_force`] to overwrite those changes, or
+    /// [`StoreManager::save_or_resolve`] to merge them.
     pub fn save(&mut self) -> Result<(), StoreError> {
-        self.store.write(&mut self.handle)
+        self.write_directly(Origin::Modification, Self::write_checked)
+    }
+
+    /// Writes the current state of the store to the storage, overwriting any external changes.
+    pub fn save_force(&mut self) -> Result<(), StoreError> {
+        self.write_directly(Origin::Modification, |manager| {
+            if manager.locked {
+                manager.store.write_unlocked(&mut manager.handle)
+            } else {
+                manager.store.write(&mut manager.handle)
+            }
+        })
+    }
+
+    /// Writes the current state of the store to the storage like [`StoreManager::save`], but
+    /// calls `resolve` when the store file was modified externally.
+    ///
+    /// `resolve` receives the in-memory store and the version currently on disk, and returns the
+    /// store to keep, which is then written. The store stays locked in between, so no other
+    /// process can write it while the conflict is being resolved.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rusty_store::{Storage, StoreHandle, StoreManager, Storing};
+    /// # use serde::{Deserialize, Serialize};
+    /// # #[derive(Serialize, Deserialize, Default, Storing)]
+    /// # pub struct MyStore {
+    /// #     pub count: u32,
+    /// # }
+    /// let storage = Storage::new("APP_ID");
+    /// let mut manager = StoreManager::<MyStore>::new(&storage, "my_store_id")
+    ///        .expect("Failed to create StoreManager");
+    ///
+    /// manager.get_store_mut().count += 1;
+    /// manager
+    ///     .save_or_resolve(|ours, theirs| MyStore {
+    ///         count: ours.count.max(theirs.count),
+    ///     })
+    ///     .expect("Failed to save store");
+    /// ```
+    pub fn save_or_resolve<F>(&mut self, resolve: F) -> Result<(), StoreError>
+    where
+        F: FnOnce(&T, T) -> T,
+    {
+        self.write_directly(Origin::Modification, |manager| {
+            let store_id = manager.handle.store_id().to_owned();
+            let _lock = if manager.locked {
+                None
+            } else {
+                Some(manager.store.lock::<T>(&store_id, LockMode::Exclusive)?)
+            };
+
+            match manager.store.ensure_unchanged(&manager.handle) {
+                Ok(()) => {}
+                Err(StoreError::Conflict(_)) => {
+                    let theirs = manager.store.read_current::<T>(&store_id)?;
+                    let resolved = resolve(manager.handle.get_store(), theirs);
+                    manager.handle.set_store(resolved);
+                }
+                Err(err) => return Err(err),
+            }
+
+            manager.store.write_unlocked(&mut manager.handle)
+        })
+    }
+
+    /// Restores the most recent readable backup of the store, see [`Storage::restore_last_good`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rusty_store::{Storage, StoreHandle, StoreManager, Storing};
+    /// # use serde::{Deserialize, Serialize};
+    /// # #[derive(Serialize, Deserialize, Default, Storing)]
+    /// # pub struct MyStore {
+    /// #     pub count: u32,
+    /// # }
+    /// let storage = Storage::new("APP_ID").with_backups(3);
+    /// let mut manager = StoreManager::<MyStore>::new(&storage, "my_store_id")
+    ///        .expect("Failed to create StoreManager");
+    ///
+    /// if manager.restore_last_good().is_ok() {
+    ///     println!("Count: {}", manager.get_store().count);
+    /// }
+    /// ```
+    pub fn restore_last_good(&mut self) -> Result<Backup, StoreError> {
+        self.flush()?;
+        self.write_directly(Origin::Modification, |manager| {
+            if manager.locked {
+                manager
+                    .store
+                    .restore_last_good_unlocked(&mut manager.handle)
+            } else {
+                manager.store.restore_last_good(&mut manager.handle)
+            }
+        })
+    }
+
+    /// Acquires an exclusive lock on the store, shared with other processes using the same
+    /// `Storage` directories, and reloads the store from disk.
+    ///
+    /// The lock is held until the returned guard is dropped, so a read-modify-write cycle made
+    /// through it cannot interleave with writes from other processes. Other processes wait for
+    /// the lock, or fail with `StoreError::Lock` once the timeout set by
+    /// [`Storage::with_lock_timeout`] expires.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rusty_store::{Storage, StoreHandle, StoreManager, Storing};
+    /// # use serde::{Deserialize, Serialize};
+    /// # #[derive(Serialize, Deserialize, Default, Storing)]
+    /// # pub struct MyStore {
+    /// #     pub count: u32,
+    /// # }
+    /// let storage = Storage::new("APP_ID");
+    /// let mut manager = StoreManager::<MyStore>::new(&storage, "my_store_id")
+    ///        .expect("Failed to create StoreManager");
+    ///
+    /// let mut locked = manager.lock_exclusive().expect("Failed to lock store");
+    /// locked.modify_store(|store| store.count += 1).expect("Failed to write store modifications");
+    /// ```
+    pub fn lock_exclusive(&mut self) -> Result<LockedStoreManager<'_, T>, StoreError> {
+        let lock = self
+            .store
+            .lock::<T>(self.handle.store_id(), LockMode::Exclusive)?;
+
+        self.locked = true;
+        if let Err(err) = self.read() {
+            self.locked = false;
+            return Err(err);
+        }
+
+        Ok(LockedStoreManager {
+            manager: self,
+            _lock: lock,
+        })
+    }
+
+    /// Enables autosave: instead of writing the store right away, modifications made through
+    /// [`StoreManager::modify_store`], [`StoreManager::try_modify_store`] and
+    /// [`StoreManager::edit`] are written by a background thread once no other modification
+    /// happened for `delay`.
+    ///
+    /// This is meant for stores which change at a high frequency, such as window geometry.
+    /// Errors of background writes are logged and returned by the next [`StoreManager::flush`];
+    /// unlike direct writes, they cannot roll back the in-memory store.
+    /// Pending modifications are flushed when autosave is disabled or the manager is dropped.
+    /// [`StoreManager::save`] still writes right away.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use rusty_store::{Storage, StoreHandle, StoreManager, Storing};
+    /// # use serde::{Deserialize, Serialize};
+    /// # #[derive(Serialize, Deserialize, Default, Storing)]
+    /// # pub struct MyStore {
+    /// #     pub count: u32,
+    /// # }
+    /// let storage = Storage::new("APP_ID");
+    /// let mut manager = StoreManager::<MyStore>::new(&storage, "my_store_id")
+    ///        .expect("Failed to create StoreManager");
+    ///
+    /// manager.enable_autosave(Duration::from_millis(500));
+    ///
+    /// for _ in 0..1000 {
+    ///     // Written once, 500ms after the last modification
+    ///     manager.modify_store(|store| store.count += 1).expect("Failed to modify store");
+    /// }
+    ///
+    /// manager.flush().expect("Failed to write store modifications");
+    /// ```
+    pub fn enable_autosave(&mut self, delay: Duration)
+    where
+        T: 'static,
+    {
+        if let Some(fingerprint) = self
+            .autosave
+            .take()
+            .and_then(|autosave| autosave.stop(self.locked))
+        {
+            self.handle.set_fingerprint(fingerprint);
+        }
+        self.autosave = Some(Autosave::start(
+            self.store.clone(),
+            self.handle.store_id().to_owned(),
+            self.handle.fingerprint(),
+            delay,
+        ));
+    }
+
+    /// Flushes pending modifications and disables autosave.
+    pub fn disable_autosave(&mut self) -> Result<(), StoreError> {
+        let result = self.flush();
+        if let Some(fingerprint) = self
+            .autosave
+            .take()
+            .and_then(|autosave| autosave.stop(self.locked))
+        {
+            self.handle.set_fingerprint(fingerprint);
+        }
+        result
+    }
+
+    /// Returns whether autosave is enabled.
+    pub fn is_autosave_enabled(&self) -> bool {
+        self.autosave.is_some()
+    }
+
+    /// Writes modifications still pending in autosave right away, and returns the error of the
+    /// last background write if it failed. Does nothing when autosave is disabled.
+    pub fn flush(&mut self) -> Result<(), StoreError> {
+        let Some(autosave) = &self.autosave else {
+            return Ok(());
+        };
+
+        if let Some(fingerprint) = autosave.flush(self.locked)? {
+            self.handle.set_fingerprint(fingerprint);
+        }
+        Ok(())
+    }
+
+    /// Registers `callback` to be called with the previous and the new store whenever a
+    /// modification is committed, and whenever [`StoreManager::get_store_alive`] loads a store
+    /// which changed on disk.
+    ///
+    /// A modification is committed once it is saved, or handed to autosave when enabled.
+    /// Callbacks are not called when the store is unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rusty_store::{Storage, StoreHandle, StoreManager, Storing};
+    /// # use serde::{Deserialize, Serialize};
+    /// # #[derive(Serialize, Deserialize, Default, Storing)]
+    /// # pub struct MyStore {
+    /// #     pub count: u32,
+    /// # }
+    /// let storage = Storage::new("APP_ID");
+    /// let mut manager = StoreManager::<MyStore>::new(&storage, "my_store_id")
+    ///        .expect("Failed to create StoreManager");
+    ///
+    /// let subscription = manager.subscribe(|old, new| {
+    ///     println!("Count changed from {} to {}", old.count, new.count);
+    /// });
+    ///
+    /// manager.modify_store(|store| store.count += 1).expect("Failed to write store modifications");
+    ///
+    /// manager.unsubscribe(subscription);
+    /// ```
+    pub fn subscribe<F>(&mut self, callback: F) -> Subscription
+    where
+        F: Fn(&T, &T) + Send + Sync + 'static,
+    {
+        if self.committed.is_none() {
+            self.committed = self.take_snapshot();
+        }
+        self.observers.subscribe(callback)
+    }
+
+    /// Removes a callback registered with [`StoreManager::subscribe`].
+    /// Returns whether it was registered.
+    pub fn unsubscribe(&mut self, subscription: Subscription) -> bool {
+        let removed = self.observers.unsubscribe(subscription);
+        if !self.tracks_commits() {
+            self.committed = None;
+        }
+        removed
+    }
+
+    /// Enables the undo history, keeping up to `depth` previous versions of the store.
+    ///
+    /// Every committed modification can then be reverted with [`StoreManager::undo`] and
+    /// reapplied with [`StoreManager::redo`]. Versions loaded from disk by
+    /// [`StoreManager::get_store_alive`] are not recorded.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rusty_store::{Storage, StoreHandle, StoreManager, Storing};
+    /// # use serde::{Deserialize, Serialize};
+    /// # #[derive(Serialize, Deserialize, Default, Storing)]
+    /// # pub struct MyStore {
+    /// #     pub count: u32,
+    /// # }
+    /// let storage = Storage::new("APP_ID");
+    /// let mut manager = StoreManager::<MyStore>::new(&storage, "my_store_id")
+    ///        .expect("Failed to create StoreManager");
+    ///
+    /// manager.enable_history(100);
+    ///
+    /// manager.modify_store(|store| store.count = 10).expect("Failed to write store modifications");
+    /// manager.undo().expect("Failed to undo");
+    /// manager.redo().expect("Failed to redo");
+    ///
+    /// assert_eq!(manager.get_store().count, 10);
+    /// ```
+    pub fn enable_history(&mut self, depth: usize) {
+        if self.committed.is_none() {
+            self.committed = self.take_snapshot();
+        }
+        self.history = Some(History::new(depth));
+    }
+
+    /// Disables the undo history and forgets the recorded versions.
+    pub fn disable_history(&mut self) {
+        self.history = None;
+        if !self.tracks_commits() {
+            self.committed = None;
+        }
+    }
+
+    /// Forgets the versions recorded in the undo history.
+    pub fn clear_history(&mut self) {
+        if let Some(history) = &mut self.history {
+            history.clear();
+        }
+    }
+
+    /// Returns whether [`StoreManager::undo`] has a version to restore.
+    pub fn can_undo(&self) -> bool {
+        self.history.as_ref().is_some_and(History::can_undo)
+    }
+
+    /// Returns whether [`StoreManager::redo`] has a version to restore.
+    pub fn can_redo(&self) -> bool {
+        self.history.as_ref().is_some_and(History::can_redo)
+    }
+
+    /// Reverts the last committed modification and saves the restored store like
+    /// [`StoreManager::save`]. Modifications which were not committed are discarded.
+    ///
+    /// Returns `false` if there was nothing to undo. If the store cannot be saved, it is left
+    /// unchanged and the version stays available to undo.
+    pub fn undo(&mut self) -> Result<bool, StoreError> {
+        self.travel(Origin::Undo)
+    }
+
+    /// Reapplies the last modification reverted by [`StoreManager::undo`] and saves the store
+    /// like [`StoreManager::save`].
+    ///
+    /// Returns `false` if there was nothing to redo. Any new modification clears the versions
+    /// available to redo.
+    pub fn redo(&mut self) -> Result<bool, StoreError> {
+        self.travel(Origin::Redo)
+    }
+
+    /// Commits a modification of the store: writes it, or hands it to autosave when enabled.
+    pub(crate) fn commit(&mut self) -> Result<(), StoreError> {
+        match &self.autosave {
+            Some(autosave) => {
+                autosave.schedule(self.store.serialize(self.handle.get_store())?);
+                self.record_commit(Origin::Modification);
+                Ok(())
+            }
+            None => self.save(),
+        }
+    }
+
+    /// Restores the version of the store at the top of the undo or redo stack and saves it.
+    fn travel(&mut self, origin: Origin) -> Result<bool, StoreError> {
+        let Some(target) = self
+            .history
+            .as_mut()
+            .and_then(|history| history.pop(origin))
+        else {
+            return Ok(false);
+        };
+
+        let result = Snapshot::take(self.handle.get_store()).and_then(|previous| {
+            self.handle.set_store(target.restore()?);
+            self.write_directly(origin, Self::write_checked)
+                .map_err(|err| self.rolled_back(&previous, err))
+        });
+
+        match result {
+            Ok(()) => Ok(true),
+            Err(err) => {
+                if let Some(history) = &mut self.history {
+                    history.unpop(target, origin);
+                }
+                Err(err)
+            }
+        }
+    }
+
+    pub(crate) fn store_path(&self) -> PathBuf {
+        self.store.store_path::<T>(self.handle.store_id())
+    }
+
+    /// Returns whether the last committed version of the store has to be kept.
+    fn tracks_commits(&self) -> bool {
+        !self.observers.is_empty() || self.history.is_some()
+    }
+
+    /// Records a newly committed version of the store in the history and calls the observers,
+    /// if it differs from the previous one.
+    fn record_commit(&mut self, origin: Origin) {
+        if !self.tracks_commits() {
+            return;
+        }
+
+        let Some(current) = self.take_snapshot() else {
+            return;
+        };
+        let Some(committed) = self.committed.replace(current.clone()) else {
+            return;
+        };
+        if committed.same_store::<T>(&current) {
+            return;
+        }
+
+        if let Some(history) = &mut self.history {
+            history.record(committed.clone(), origin);
+        }
+        if self.observers.is_empty() {
+            return;
+        }
+
+        match committed.restore::<T>() {
+            Ok(old) => self.observers.notify(&old, self.handle.get_store()),
+            Err(err) => warn!(
+                "Failed to restore previous store with id: {} for observers, error: {:?}",
+                self.handle.store_id(),
+                err
+            ),
+        }
+    }
+
+    fn take_snapshot(&self) -> Option<Snapshot> {
+        Snapshot::take(self.handle.get_store())
+            .inspect_err(|err| {
+                warn!(
+                    "Failed to snapshot store with id: {}, error: {:?}",
+                    self.handle.store_id(),
+                    err
+                )
+            })
+            .ok()
+    }
+
+    /// Runs `operation`, which writes the store directly, in place of autosave: its pending
+    /// modification is superseded by the in-memory store, and it learns about the new file.
+    fn write_directly<R, F>(&mut self, origin: Origin, operation: F) -> Result<R, StoreError>
+    where
+        F: FnOnce(&mut Self) -> Result<R, StoreError>,
+    {
+        if let Some(fingerprint) = self.autosave.as_ref().and_then(Autosave::discard) {
+            self.handle.set_fingerprint(fingerprint);
+        }
+
+        let result = operation(self);
+
+        if let Some(autosave) = &self.autosave {
+            autosave.set_fingerprint(self.handle.fingerprint());
+        }
+        if result.is_ok() {
+            self.record_commit(origin);
+        }
+        result
+    }
+
+    fn write_checked(&mut self) -> Result<(), StoreError> {
+        if self.locked {
+            self.store.write_if_unchanged_unlocked(&mut self.handle)
+        } else {
+            self.store.write_if_unchanged(&mut self.handle)
+        }
+    }
+
+    /// Applies `change` to the store, restoring `snapshot` if it panics.
+    fn apply<R, F>(&mut self, snapshot: &Snapshot, change: F) -> R
+    where
+        F: FnOnce(&mut T) -> R,
+    {
+        let store = self.handle.get_store_mut();
+        match panic::catch_unwind(AssertUnwindSafe(|| change(store))) {
+            Ok(value) => value,
+            Err(payload) => {
+                if let Err(err) = self.rollback(snapshot) {
+                    warn!(
+                        "Failed to roll back store with id: {} after a panic, error: {:?}",
+                        self.handle.store_id(),
+                        err
+                    );
+                }
+                panic::resume_unwind(payload)
+            }
+        }
+    }
+
+    /// Restores the in-memory store to `snapshot` after a failed save.
+    pub(crate) fn rollback(&mut self, snapshot: &Snapshot) -> Result<(), StoreError> {
+        self.handle.set_store(snapshot.restore()?);
+        Ok(())
+    }
+
+    /// Restores the in-memory store to `snapshot` after a save failed with `error`, and returns
+    /// the error to report: `error`, or [`StoreError::Rollback`] if the store was not restored.
+    pub(crate) fn rolled_back(&mut self, snapshot: &Snapshot, error: StoreError) -> StoreError {
+        match self.rollback(snapshot) {
+            Ok(()) => error,
+            Err(rollback) => {
+                warn!(
+                    "Failed to roll back store with id: {}, error: {:?}",
+                    self.handle.store_id(),
+                    rollback
+                );
+                StoreError::Rollback {
+                    error: Box::new(error),
+                    rollback: Box::new(rollback),
+                }
+            }
+        }
+    }
+
+    fn read(&mut self) -> Result<(), StoreError> {
+        self.flush()?;
+
+        if self.locked {
+            self.store.read_unlocked(&mut self.handle)?;
+        } else {
+            self.store.read(&mut self.handle)?;
+        }
+
+        if let Some(autosave) = &self.autosave {
+            autosave.set_fingerprint(self.handle.fingerprint());
+        }
+        self.record_commit(Origin::Reload);
+        Ok(())
+    }
+}
+
+impl<T: Storing + Clone> Clone for StoreManager<T> {
+    /// Clones the manager and its store. The clone never shares an exclusive lock, does not
+    /// autosave, and has no observers nor history.
+    fn clone(&self) -> Self {
+        Self {
+            store: self.store.clone(),
+            handle: self.handle.clone(),
+            locked: false,
+            autosave: None,
+            observers: Observers::default(),
+            history: None,
+            committed: None,
+        }
+    }
+}
+
+/// The error returned by [`StoreManager::try_modify_store`].
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum ModifyError<E> {
+    /// The modification returned an error. The store was left unchanged and nothing was written.
+    #[error("Modification rejected: {0}")]
+    Rejected(E),
+
+    /// The modification succeeded, but the store could not be saved and was rolled back.
+    #[error(transparent)]
+    Store(#[from] StoreError),
+}
+
+/// A `StoreManager` holding an exclusive lock on its store, returned by
+/// [`StoreManager::lock_exclusive`].
+///
+/// It dereferences to the `StoreManager`, so the store can be read, modified and saved as usual
+/// while the lock is held. The lock is released when it is dropped.
+#[derive(Debug)]
+pub struct LockedStoreManager<'a, T: Storing> {
+    manager: &'a mut StoreManager<T>,
+    _lock: StoreLock,
+}
+
+impl<T: Storing> Deref for LockedStoreManager<'_, T> {
+    type Target = StoreManager<T>;
+
+    fn deref(&self) -> &Self::Target {
+        self.manager
+    }
+}
+
+impl<T: Storing> DerefMut for LockedStoreManager<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.manager
+    }
+}
+
+impl<T: Storing> Drop for LockedStoreManager<'_, T> {
+    fn drop(&mut self) {
+        self.manager.locked = false;
     }
 }
@@ -0,0 +1,251 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Debug};
+use std::fs;
+
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+
+use crate::conflict::Fingerprint;
+use crate::format::Format;
+use crate::storage::{MissingFields, Storage, StoreError, Storing};
+use crate::value::Value;
+
+type Step = Box<dyn Fn(&mut Value) -> Result<(), String> + Send + Sync>;
+
+/// The steps upgrading the files of older versions of a store to its [`Storing::VERSION`],
+/// returned by [`Storing::migrations`].
+///
+/// Each step turns the untyped store of one version into the next, before it is deserialized.
+/// Versions without a step are upgraded as is, which suits changes such as adding a field with a
+/// `#[serde(default)]`.
+///
+/// # Example
+///
+/// ```
+/// use rusty_store::{Migrations, Storing, Value};
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize, Default)]
+/// pub struct Settings {
+///     pub theme: String,
+///     pub font_size: u32,
+/// }
+///
+/// impl Storing for Settings {
+///     const VERSION: u32 = 2;
+///
+///     fn migrations() -> Migrations {
+///         Migrations::new()
+///             // Version 1 named the theme `color_scheme`.
+///             .step(1, |store| {
+///                 store.rename("color_scheme", "theme");
+///                 Ok(())
+///             })
+///             // Version 2 added the font size.
+///             .step(2, |store| {
+///                 store.insert("font_size", Value::from(12));
+///                 Ok(())
+///             })
+///     }
+/// }
+/// ```
+#[derive(Default)]
+pub struct Migrations {
+    steps: BTreeMap<u32, Step>,
+}
+
+impl Migrations {
+    /// Creates an empty migration chain.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the step upgrading stores of version `version - 1` to `version`, returning a
+    /// message describing the problem if the store cannot be upgraded.
+    pub fn step<F>(mut self, version: u32, step: F) -> Self
+    where
+        F: Fn(&mut Value) -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.steps.insert(version, Box::new(step));
+        self
+    }
+
+    /// Upgrades `store` from version `from` to version `to`.
+    fn run(&self, store: &mut Value, from: u32, to: u32) -> Result<(), StoreError> {
+        for version in from + 1..=to {
+            if let Some(step) = self.steps.get(&version) {
+                debug!("Migrating store to version {}", version);
+                step(store).map_err(|message| StoreError::Migration { version, message })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Debug for Migrations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.steps.keys()).finish()
+    }
+}
+
+/// The file layout of versioned stores.
+#[derive(Serialize)]
+struct VersionedRef<'a, T> {
+    version: u32,
+    store: &'a T,
+}
+
+#[derive(Deserialize)]
+struct Versioned<T> {
+    version: u32,
+    store: T,
+}
+
+/// A store read from its file, the version it was migrated from if it was, and whether fields
+/// missing from the file were filled from the default store.
+pub(crate) struct Parsed<T> {
+    pub(crate) store: T,
+    pub(crate) migrated_from: Option<u32>,
+    pub(crate) filled: bool,
+}
+
+/// Serializes a store in `format`, in the versioned layout if the store is versioned.
+pub(crate) fn serialize<T: Storing>(format: Format, store: &T) -> Result<Vec<u8>, StoreError> {
+    match T::VERSION {
+        0 => format.serialize(store, T::pretty_config()),
+        version => format.serialize(&VersionedRef { version, store }, T::pretty_config()),
+    }
+}
+
+/// Deserializes a store from contents in `format`, migrating them first if they were written
+/// by an older version of the store, and filling the fields they miss according to
+/// `missing_fields`.
+pub(crate) fn parse<T: Storing>(
+    format: Format,
+    contents: &[u8],
+    missing_fields: MissingFields,
+) -> Result<Parsed<T>, StoreError> {
+    let fill = missing_fields != MissingFields::Fail;
+    let typed = match T::VERSION {
+        0 => format.parse::<T>(contents).map(|store| (0, store)),
+        _ => format
+            .parse::<Versioned<T>>(contents)
+            .map(|versioned| (versioned.version, versioned.store)),
+    };
+    let typed = match typed {
+        Ok((version, store)) if version == T::VERSION => {
+            return Ok(Parsed {
+                store,
+                migrated_from: None,
+                filled: false,
+            })
+        }
+        Ok((version, _)) => Ok(version),
+        Err(err) if T::VERSION == 0 && !fill => return Err(err),
+        Err(err) => Err(err),
+    };
+
+    let Some(value) = Value::parse(format, contents) else {
+        return Err(match typed {
+            Ok(version) if version > T::VERSION => StoreError::UnsupportedVersion {
+                version,
+                supported: T::VERSION,
+            },
+            Ok(_) => StoreError::Migration {
+                version: T::VERSION,
+                message: format!("{:?} stores cannot be migrated", format),
+            },
+            Err(err) => err,
+        });
+    };
+
+    let (version, mut store) = match T::VERSION {
+        0 => (0, value),
+        _ => split_version(value),
+    };
+    if version > T::VERSION {
+        return Err(StoreError::UnsupportedVersion {
+            version,
+            supported: T::VERSION,
+        });
+    }
+    let typed = typed.err();
+
+    if version < T::VERSION {
+        info!("Migrating store from version {} to {}", version, T::VERSION);
+        T::migrations().run(&mut store, version, T::VERSION)?;
+    }
+    let filled =
+        fill && Value::from_store(&T::default()).is_some_and(|defaults| store.fill(defaults));
+    if version == T::VERSION && !filled {
+        if let Some(err) = typed {
+            // The file has the current version and misses no field, it is simply broken.
+            return Err(err);
+        }
+    }
+
+    match T::deserialize(store) {
+        Ok(store) => Ok(Parsed {
+            store,
+            migrated_from: (version < T::VERSION).then_some(version),
+            filled,
+        }),
+        Err(err) => Err(match typed {
+            // A file of the current version is broken whatever was filled in.
+            Some(typed) if version == T::VERSION => typed,
+            _ => StoreError::Migration {
+                version: T::VERSION,
+                message: err.to_string(),
+            },
+        }),
+    }
+}
+
+/// Splits a store in the versioned layout into its version and the store itself. Stores written
+/// before they were versioned are version `0`.
+fn split_version(value: Value) -> (u32, Value) {
+    let version = match &value {
+        Value::Struct(fields) if fields.len() == 2 => value.get("version"),
+        Value::Map(entries) if entries.len() == 2 => value.get("version"),
+        _ => None,
+    };
+    let version = version
+        .and_then(Value::as_integer)
+        .and_then(|version| u32::try_from(version).ok());
+
+    match (version, value) {
+        (Some(version), mut value) if value.get("store").is_some() => {
+            let store = value.remove("store").unwrap_or(Value::Unit);
+            (version, store)
+        }
+        (_, value) => (0, value),
+    }
+}
+
+impl Storage {
+    /// Rewrites the file of a store migrated from `version`, first copying the file to
+    /// `<store_id>.<extension>.v<version>.bak`. Assumes the caller holds the store's lock.
+    pub(crate) fn rewrite_migrated<T: Storing>(
+        &self,
+        store_id: &str,
+        version: u32,
+        store: &T,
+    ) -> Result<Fingerprint, StoreError> {
+        let path = self.store_path::<T>(store_id);
+        let mut name = path.file_name().unwrap_or_default().to_os_string();
+        name.push(format!(".v{}.bak", version));
+        let backup = path.with_file_name(name);
+
+        debug!("Backing up store before migration at path: {:?}", backup);
+        fs::copy(&path, &backup).map_err(StoreError::Backup)?;
+
+        let fingerprint = self.write_contents::<T>(store_id, &self.serialize(store)?)?;
+        info!(
+            "Migrated store at path: {:?} from version {} to {}",
+            path,
+            version,
+            T::VERSION
+        );
+        Ok(fingerprint)
+    }
+}
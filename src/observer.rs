@@ -0,0 +1,59 @@
+use std::fmt::{self, Debug};
+use std::sync::Arc;
+
+/// Identifies a callback registered with [`StoreManager::subscribe`](crate::StoreManager::subscribe).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Subscription(usize);
+
+type Callback<T> = Arc<dyn Fn(&T, &T) + Send + Sync>;
+
+/// The callbacks notified of changes to a store, with the previous and the new store.
+pub(crate) struct Observers<T> {
+    next: usize,
+    callbacks: Vec<(Subscription, Callback<T>)>,
+}
+
+impl<T> Observers<T> {
+    pub(crate) fn subscribe<F>(&mut self, callback: F) -> Subscription
+    where
+        F: Fn(&T, &T) + Send + Sync + 'static,
+    {
+        let subscription = Subscription(self.next);
+        self.next += 1;
+        self.callbacks.push((subscription, Arc::new(callback)));
+        subscription
+    }
+
+    pub(crate) fn unsubscribe(&mut self, subscription: Subscription) -> bool {
+        let len = self.callbacks.len();
+        self.callbacks.retain(|(id, _)| *id != subscription);
+        self.callbacks.len() != len
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.callbacks.is_empty()
+    }
+
+    pub(crate) fn notify(&self, old: &T, new: &T) {
+        for (_, callback) in &self.callbacks {
+            callback(old, new);
+        }
+    }
+}
+
+impl<T> Default for Observers<T> {
+    fn default() -> Self {
+        Self {
+            next: 0,
+            callbacks: Vec::new(),
+        }
+    }
+}
+
+impl<T> Debug for Observers<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Observers")
+            .field("count", &self.callbacks.len())
+            .finish()
+    }
+}
@@ -0,0 +1,329 @@
+use std::ops::Range;
+
+use log::debug;
+
+use crate::format::Format;
+
+/// Patches the document `old` so it holds the same store as the freshly serialized `new`,
+/// rewriting only the values that changed. Comments, ordering and formatting elsewhere in `old`
+/// are kept.
+///
+/// Returns `None` when the format cannot be patched or either document cannot be patched
+/// safely, in which case the store file is replaced by `new` as usual.
+pub(crate) fn patch(format: Format, old: &[u8], new: &[u8]) -> Option<Vec<u8>> {
+    let old = std::str::from_utf8(old).ok()?;
+    let new = std::str::from_utf8(new).ok()?;
+
+    let patched = match format {
+        Format::Ron => ron_patch::patch(old, new),
+        #[cfg(feature = "toml")]
+        Format::Toml => toml_patch::patch(old, new),
+        #[allow(unreachable_patterns)]
+        _ => None,
+    };
+
+    if patched.is_none() {
+        debug!("Could not patch store, it is rewritten whole");
+    }
+    patched.map(String::into_bytes)
+}
+
+/// A replacement of `range` of the old document by `text`.
+struct Edit {
+    range: Range<usize>,
+    text: String,
+}
+
+/// Applies `edits` to `src`. Edits must not overlap, edits starting at the same position are
+/// applied so their texts appear in the order they were queued.
+fn apply(src: &str, edits: Vec<Edit>) -> String {
+    let mut edits: Vec<(usize, Edit)> = edits.into_iter().enumerate().collect();
+    edits.sort_by_key(|(i, edit)| (edit.range.start, edit.range.end, *i));
+
+    let mut patched = src.to_owned();
+    for (_, edit) in edits.into_iter().rev() {
+        patched.replace_range(edit.range, &edit.text);
+    }
+    patched
+}
+
+mod ron_patch {
+    use super::{apply, Edit};
+    use crate::syntax::{self, Item, Kind, Node};
+
+    struct Patcher<'a> {
+        old: &'a str,
+        new: &'a str,
+        edits: Vec<Edit>,
+    }
+
+    pub(super) fn patch(old: &str, new: &str) -> Option<String> {
+        let old_root = syntax::parse(old)?;
+        let new_root = syntax::parse(new)?;
+
+        let mut patcher = Patcher {
+            old,
+            new,
+            edits: Vec::new(),
+        };
+        patcher.node(&old_root, &new_root);
+        let patched = apply(old, patcher.edits);
+
+        // The patched document must hold exactly the new store.
+        let expected: ron::Value = ron::from_str(new).ok()?;
+        let actual: ron::Value = ron::from_str(&patched).ok()?;
+        (actual == expected).then_some(patched)
+    }
+
+    impl Patcher<'_> {
+        fn node(&mut self, old: &Node, new: &Node) {
+            match (&old.kind, &new.kind) {
+                (
+                    Kind::Fields {
+                        open: old_open,
+                        items: old_items,
+                    },
+                    Kind::Fields {
+                        open: new_open,
+                        items: new_items,
+                    },
+                ) if self.same_name(old, *old_open, new, *new_open) => {
+                    self.fields(old, new, *old_open, old_items, new_items)
+                }
+                (
+                    Kind::Seq {
+                        open: old_open,
+                        items: old_items,
+                    },
+                    Kind::Seq {
+                        open: new_open,
+                        items: new_items,
+                    },
+                ) if self.same_name(old, *old_open, new, *new_open)
+                    && old_items.len() == new_items.len() =>
+                {
+                    for (old, new) in old_items.iter().zip(new_items) {
+                        self.node(&old.value, &new.value);
+                    }
+                }
+                (Kind::Leaf, Kind::Leaf) if self.same_leaf(old, new) => {}
+                _ => self.replace(old, new),
+            }
+        }
+
+        fn same_name(&self, old: &Node, old_open: usize, new: &Node, new_open: usize) -> bool {
+            self.old[old.start..old_open].trim() == self.new[new.start..new_open].trim()
+        }
+
+        /// Whether two leaves hold the same value, such as `1.0` and `1.00`. Identifiers, such as
+        /// unit variants, are only equal when spelled the same.
+        fn same_leaf(&self, old: &Node, new: &Node) -> bool {
+            let old = &self.old[old.start..old.end];
+            let new = &self.new[new.start..new.end];
+            if old == new {
+                return true;
+            }
+            let is_ident = |text: &str| text.starts_with(|c: char| c.is_alphabetic() || c == '_');
+            if is_ident(old) || is_ident(new) {
+                return false;
+            }
+            match (
+                ron::from_str::<ron::Value>(old),
+                ron::from_str::<ron::Value>(new),
+            ) {
+                (Ok(old), Ok(new)) => old == new,
+                _ => false,
+            }
+        }
+
+        fn replace(&mut self, old: &Node, new: &Node) {
+            self.edits.push(Edit {
+                range: old.start..old.end,
+                text: self.new[new.start..new.end].to_owned(),
+            });
+        }
+
+        /// Patches the fields of a struct or the entries of a map: removed ones are deleted,
+        /// kept ones are patched in place and added ones are appended after the last kept one.
+        fn fields(
+            &mut self,
+            old: &Node,
+            new: &Node,
+            open: usize,
+            old_items: &[Item],
+            new_items: &[Item],
+        ) {
+            let key = |src: &str, item: &Item| {
+                let (start, end) = item.key.unwrap_or_default();
+                src[start..end].to_owned()
+            };
+            let old_keys: Vec<String> = old_items.iter().map(|item| key(self.old, item)).collect();
+            let new_keys: Vec<String> = new_items.iter().map(|item| key(self.new, item)).collect();
+
+            let Some(anchor) = old_keys.iter().rposition(|key| new_keys.contains(key)) else {
+                // Nothing is kept, there is no formatting worth preserving.
+                return self.replace(old, new);
+            };
+
+            for (i, item) in old_items.iter().enumerate() {
+                match new_keys.iter().position(|key| *key == old_keys[i]) {
+                    Some(j) => self.node(&item.value, &new_items[j].value),
+                    None => {
+                        let previous_end = if i == 0 {
+                            open + 1
+                        } else {
+                            old_items[i - 1].end
+                        };
+                        // Comments on the line of the previous item stay with it.
+                        let start = self.old[previous_end..item.start()]
+                            .find('\n')
+                            .map_or(previous_end, |offset| previous_end + offset);
+                        self.edits.push(Edit {
+                            range: start..item.end,
+                            text: String::new(),
+                        });
+                    }
+                }
+            }
+
+            let added: Vec<&Item> = new_items
+                .iter()
+                .zip(&new_keys)
+                .filter(|(_, key)| !old_keys.contains(key))
+                .map(|(item, _)| item)
+                .collect();
+            if added.is_empty() {
+                return;
+            }
+
+            // Items after the anchor are all removed, the added ones go on the anchor's line.
+            let boundary = old_items.get(anchor + 1).map_or(old.end - 1, Item::start);
+            let anchor = &old_items[anchor];
+            let entry = |item: &Item| {
+                let (start, end) = item.key.unwrap_or_default();
+                format!(
+                    "{}: {}",
+                    &self.new[start..end],
+                    &self.new[item.value.start..item.value.end]
+                )
+            };
+
+            match self.old[anchor.end..boundary].find('\n') {
+                Some(offset) => {
+                    let line_start = self.old[..anchor.start()]
+                        .rfind('\n')
+                        .map_or(0, |position| position + 1);
+                    let indent = &self.old[line_start..anchor.start()];
+                    let indent = if indent.trim().is_empty() { indent } else { "" };
+                    if !anchor.comma {
+                        self.edits.push(Edit {
+                            range: anchor.value.end..anchor.value.end,
+                            text: ",".to_owned(),
+                        });
+                    }
+                    let text = added
+                        .iter()
+                        .map(|item| format!("\n{}{},", indent, entry(item)))
+                        .collect();
+                    let position = anchor.end + offset;
+                    self.edits.push(Edit {
+                        range: position..position,
+                        text,
+                    });
+                }
+                None => {
+                    let entries: Vec<String> = added.iter().map(|item| entry(item)).collect();
+                    let text = if anchor.comma {
+                        format!(" {},", entries.join(", "))
+                    } else {
+                        format!(", {}", entries.join(", "))
+                    };
+                    self.edits.push(Edit {
+                        range: anchor.end..anchor.end,
+                        text,
+                    });
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "toml")]
+mod toml_patch {
+    use toml_edit::{DocumentMut, Item, TableLike, Value};
+
+    pub(super) fn patch(old: &str, new: &str) -> Option<String> {
+        let mut document: DocumentMut = old.parse().ok()?;
+        let new_document: DocumentMut = new.parse().ok()?;
+        table(document.as_table_mut(), new_document.as_table());
+        let patched = document.to_string();
+
+        // The patched document must hold exactly the new store.
+        let expected: toml::Table = toml::from_str(new).ok()?;
+        let actual: toml::Table = toml::from_str(&patched).ok()?;
+        (actual == expected).then_some(patched)
+    }
+
+    /// Removes the keys missing from `new`, patches the kept ones and appends the added ones.
+    fn table(old: &mut dyn TableLike, new: &dyn TableLike) {
+        let removed: Vec<String> = old
+            .iter()
+            .filter(|(key, _)| !new.contains_key(key))
+            .map(|(key, _)| key.to_owned())
+            .collect();
+        for key in removed {
+            old.remove(&key);
+        }
+
+        for (key, new) in new.iter() {
+            match old.get_mut(key) {
+                Some(old) => item(old, new),
+                None => {
+                    old.insert(key, new.clone());
+                }
+            }
+        }
+    }
+
+    fn item(old: &mut Item, new: &Item) {
+        match (&mut *old, new) {
+            (Item::Table(old), Item::Table(new)) => table(old, new),
+            (Item::ArrayOfTables(old), Item::ArrayOfTables(new)) if old.len() == new.len() => {
+                for (old, new) in old.iter_mut().zip(new.iter()) {
+                    table(old, new);
+                }
+            }
+            (Item::Value(old), Item::Value(new)) => value(old, new),
+            _ => *old = new.clone(),
+        }
+    }
+
+    /// Patches a value, keeping the comments and whitespace around it.
+    fn value(old: &mut Value, new: &Value) {
+        match (&mut *old, new) {
+            (Value::InlineTable(old), Value::InlineTable(new)) => table(old, new),
+            (Value::Array(old), Value::Array(new)) if old.len() == new.len() => {
+                for (old, new) in old.iter_mut().zip(new.iter()) {
+                    value(old, new);
+                }
+            }
+            _ => {
+                if !same(old, new) {
+                    let decor = old.decor().clone();
+                    *old = new.clone();
+                    *old.decor_mut() = decor;
+                }
+            }
+        }
+    }
+
+    /// Whether two values are equal whatever their formatting, such as `0x10` and `16`.
+    fn same(old: &Value, new: &Value) -> bool {
+        let parse = |value: &Value| {
+            let mut value = value.clone();
+            value.decor_mut().clear();
+            toml::from_str::<toml::Table>(&format!("value = {}", value)).ok()
+        };
+        matches!((parse(old), parse(new)), (Some(old), Some(new)) if old == new)
+    }
+}
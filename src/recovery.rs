@@ -0,0 +1,131 @@
+use std::fmt::{self, Debug};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::{debug, warn};
+
+use crate::backup::Backup;
+use crate::storage::{Storage, StoreError, StoreHandle, Storing};
+
+/// What [`Storage::read`] does when a store file exists but cannot be parsed.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RecoveryPolicy {
+    /// Returns the parse error, leaving the file untouched.
+    #[default]
+    Fail,
+    /// Moves the broken file to `<store_id>.<extension>.corrupt-<timestamp>`, suffixed with a
+    /// counter if that name is taken, and starts from the default store.
+    MoveAside,
+    /// Restores the most recent readable backup kept by [`Storage::with_backups`].
+    /// Returns the parse error if there is none.
+    RestoreBackup,
+}
+
+/// Describes how a store that failed to parse was recovered, passed to the callback set with
+/// [`Storage::on_recovery`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Recovery {
+    /// The broken file was moved to `corrupt_path` and replaced by the default store.
+    MovedAside {
+        store_id: String,
+        corrupt_path: PathBuf,
+        error: StoreError,
+    },
+    /// The store was restored from `backup`. The broken file became the most recent backup.
+    RestoredBackup {
+        store_id: String,
+        backup: Backup,
+        error: StoreError,
+    },
+}
+
+impl Recovery {
+    /// Returns the id of the recovered store.
+    pub fn store_id(&self) -> &str {
+        match self {
+            Recovery::MovedAside { store_id, .. } | Recovery::RestoredBackup { store_id, .. } => {
+                store_id
+            }
+        }
+    }
+
+    /// Returns the error that made the store unreadable.
+    pub fn error(&self) -> &StoreError {
+        match self {
+            Recovery::MovedAside { error, .. } | Recovery::RestoredBackup { error, .. } => error,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct RecoveryCallback(pub(crate) Arc<dyn Fn(&Recovery) + Send + Sync>);
+
+impl Debug for RecoveryCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("RecoveryCallback")
+    }
+}
+
+impl Storage {
+    /// Applies the recovery policy of `T` to a store that failed to parse with `error`.
+    pub(crate) fn recover<T: Storing>(
+        &self,
+        handle: &mut StoreHandle<T>,
+        error: StoreError,
+    ) -> Result<(), StoreError> {
+        let store_id = handle.store_id().to_owned();
+        let recovery = match self.recovery_policy::<T>() {
+            RecoveryPolicy::Fail => return Err(error),
+            RecoveryPolicy::MoveAside => {
+                let path = self.store_path::<T>(&store_id);
+                let timestamp = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|since| since.as_secs())
+                    .unwrap_or_default();
+
+                // Several stores may break within a second, none is overwritten.
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                let mut corrupt_path =
+                    path.with_file_name(format!("{}.corrupt-{}", name, timestamp));
+                let mut attempt = 0;
+                while corrupt_path.exists() {
+                    attempt += 1;
+                    corrupt_path =
+                        path.with_file_name(format!("{}.corrupt-{}-{}", name, timestamp, attempt));
+                }
+
+                debug!("Moving corrupt store to path: {:?}", corrupt_path);
+                fs::rename(&path, &corrupt_path).map_err(StoreError::Write)?;
+                let fingerprint = self.store_default::<T>(path)?;
+                handle.set_store(T::default());
+                handle.set_fingerprint(fingerprint);
+
+                Recovery::MovedAside {
+                    store_id,
+                    corrupt_path,
+                    error,
+                }
+            }
+            RecoveryPolicy::RestoreBackup => match self.restore_last_good_unlocked(handle) {
+                Ok(backup) => Recovery::RestoredBackup {
+                    store_id,
+                    backup,
+                    error,
+                },
+                Err(StoreError::NoBackup(_)) => return Err(error),
+                Err(err) => return Err(err),
+            },
+        };
+
+        warn!("Recovered unreadable store: {:?}", recovery);
+        if let Some(callback) = self.recovery_callback() {
+            (callback.0)(&recovery);
+        }
+
+        Ok(())
+    }
+}
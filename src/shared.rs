@@ -0,0 +1,146 @@
+use std::ops::Deref;
+#[cfg(feature = "watcher")]
+use std::sync::Weak;
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::manager::{ModifyError, StoreManager};
+use crate::storage::{Storage, StoreError, Storing};
+
+/// A `StoreManager` shared between threads.
+///
+/// Cloning a `SharedStoreManager` is cheap and every clone manages the same store. Any number of
+/// threads can read the store at once, while modifications and saves are serialized. A thread
+/// that panics in [`SharedStoreManager::modify_store`] or
+/// [`SharedStoreManager::try_modify_store`] leaves the store as it was, and the store stays usable
+/// by the others.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rusty_store::{SharedStoreManager, Storage, Storing};
+/// # use serde::{Deserialize, Serialize};
+/// # #[derive(Serialize, Deserialize, Default, Storing)]
+/// # pub struct MyStore {
+/// #     pub count: u32,
+/// # }
+/// let storage = Storage::new("APP_ID");
+/// let manager = SharedStoreManager::<MyStore>::new(&storage, "my_store_id")
+///        .expect("Failed to create SharedStoreManager");
+///
+/// let worker = {
+///     let manager = manager.clone();
+///     std::thread::spawn(move || {
+///         manager.modify_store(|store| store.count += 1).expect("Failed to write store modifications");
+///     })
+/// };
+/// worker.join().unwrap();
+///
+/// println!("Count: {}", manager.read().count);
+/// ```
+#[derive(Debug)]
+pub struct SharedStoreManager<T: Storing> {
+    manager: Arc<RwLock<StoreManager<T>>>,
+}
+
+impl<T: Storing> SharedStoreManager<T> {
+    /// Creates a new `SharedStoreManager` by reading the store data from the provided `Storage`.
+    pub fn new(storage: &Storage, store_id: &str) -> Result<Self, StoreError> {
+        StoreManager::new(storage, store_id).map(Self::from)
+    }
+
+    /// Returns read access to the store, waiting for any modification in progress.
+    pub fn read(&self) -> SharedStoreRef<'_, T> {
+        SharedStoreRef {
+            manager: self.manager.read().unwrap_or_else(PoisonError::into_inner),
+        }
+    }
+
+    /// Reads the store from the storage and returns read access to it.
+    pub fn get_store_alive(&self) -> Result<SharedStoreRef<'_, T>, StoreError> {
+        let mut manager = self.lock();
+        manager.get_store_alive()?;
+        Ok(SharedStoreRef {
+            manager: RwLockWriteGuard::downgrade(manager),
+        })
+    }
+
+    /// Reads the store from the storage if the file changed, like
+    /// [`StoreManager::reload_if_changed`].
+    pub fn reload_if_changed(&self) -> Result<bool, StoreError> {
+        self.lock().reload_if_changed()
+    }
+
+    /// Modifies the store and commits it to the storage, like [`StoreManager::modify_store`].
+    pub fn modify_store<F>(&self, change: F) -> Result<(), StoreError>
+    where
+        F: FnMut(&mut T),
+    {
+        self.lock().modify_store(change)
+    }
+
+    /// Modifies the store with a fallible `change` and commits it to the storage, like
+    /// [`StoreManager::try_modify_store`].
+    pub fn try_modify_store<F, R, E>(&self, change: F) -> Result<R, ModifyError<E>>
+    where
+        F: FnOnce(&mut T) -> Result<R, E>,
+    {
+        self.lock().try_modify_store(change)
+    }
+
+    /// Saves the store, like [`StoreManager::save`].
+    pub fn save(&self) -> Result<(), StoreError> {
+        self.lock().save()
+    }
+
+    /// Saves the store even if it was changed by another process, like
+    /// [`StoreManager::save_force`].
+    pub fn save_force(&self) -> Result<(), StoreError> {
+        self.lock().save_force()
+    }
+
+    #[cfg(feature = "watcher")]
+    pub(crate) fn downgrade(&self) -> Weak<RwLock<StoreManager<T>>> {
+        Arc::downgrade(&self.manager)
+    }
+
+    /// Locks the manager for writing, giving access to the whole `StoreManager` API.
+    ///
+    /// Readers and other writers wait until the returned guard is dropped. If the thread panics
+    /// while holding the guard, the modifications it made in memory are kept.
+    pub fn lock(&self) -> RwLockWriteGuard<'_, StoreManager<T>> {
+        self.manager.write().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T: Storing> Clone for SharedStoreManager<T> {
+    /// Returns another handle to the same manager.
+    fn clone(&self) -> Self {
+        Self {
+            manager: Arc::clone(&self.manager),
+        }
+    }
+}
+
+impl<T: Storing> From<StoreManager<T>> for SharedStoreManager<T> {
+    fn from(manager: StoreManager<T>) -> Self {
+        Self {
+            manager: Arc::new(RwLock::new(manager)),
+        }
+    }
+}
+
+/// Read access to the store of a `SharedStoreManager`, returned by [`SharedStoreManager::read`].
+///
+/// Modifications wait until every `SharedStoreRef` is dropped.
+#[derive(Debug)]
+pub struct SharedStoreRef<'a, T: Storing> {
+    manager: RwLockReadGuard<'a, StoreManager<T>>,
+}
+
+impl<T: Storing> Deref for SharedStoreRef<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.manager.get_store()
+    }
+}
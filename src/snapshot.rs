@@ -0,0 +1,43 @@
+use ron::ser::PrettyConfig;
+
+use crate::storage::{StoreError, Storing};
+use crate::value::Value;
+
+/// A serialized copy of a store, used to bring the in-memory store back to an earlier state.
+///
+/// Snapshots go through serialization rather than `Clone`, so any `Storing` type can be
+/// snapshotted. They use the format of the store, so a snapshot restores whatever the store file
+/// would. As a consequence, fields skipped by serde are not part of a snapshot: restoring one
+/// resets them to their default, just like reading the store from disk would.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Snapshot(Vec<u8>);
+
+impl Snapshot {
+    pub(crate) fn take<T: Storing>(store: &T) -> Result<Self, StoreError> {
+        T::format()
+            .serialize(store, PrettyConfig::default())
+            .map(Snapshot)
+    }
+
+    pub(crate) fn restore<T: Storing>(&self) -> Result<T, StoreError> {
+        T::format().parse(&self.0)
+    }
+
+    /// Returns whether both snapshots hold the same store. Snapshots of equal stores may differ
+    /// when the store holds a `HashMap`, whose entries are serialized in no particular order.
+    pub(crate) fn same_store<T: Storing>(&self, other: &Self) -> bool {
+        if self == other {
+            return true;
+        }
+
+        let canonical = |snapshot: &Self| {
+            let mut value = Value::from_store(&snapshot.restore::<T>().ok()?)?;
+            value.canonicalize();
+            Some(value)
+        };
+        match (canonical(self), canonical(other)) {
+            (Some(value), Some(other)) => value == other,
+            _ => false,
+        }
+    }
+}
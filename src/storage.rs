@@ -1,19 +1,30 @@
 use ron::ser::PrettyConfig;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::fmt::Debug;
 use std::fs::{self, File, OpenOptions};
 use std::io::{Read, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
 
 use log::debug;
 use log::info;
-use log::log;
 use log::warn;
 
+use crate::compression::Compression;
+use crate::conflict::Fingerprint;
+#[cfg(feature = "encryption")]
+use crate::encryption::{KeyProvider, KeyProviderRef};
+use crate::format::Format;
+use crate::lock::{LockMode, StoreLock};
 use crate::manager::StoreManager;
+use crate::migration::{self, Migrations, Parsed};
+use crate::recovery::{Recovery, RecoveryCallback, RecoveryPolicy};
 
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum StoreError {
     #[error("RON parsing error: {0}")]
     RonParse(#[source] ron::error::SpannedError),
@@ -32,6 +43,150 @@ pub enum StoreError {
 
     #[error("Failed to write to file: {0}")]
     Write(#[source] std::io::Error),
+
+    #[error("Failed to sync to disk: {0}")]
+    Sync(#[source] std::io::Error),
+
+    #[error("Failed to lock store: {0}")]
+    Lock(#[source] std::io::Error),
+
+    #[error("Failed to back up store: {0}")]
+    Backup(#[source] std::io::Error),
+
+    #[error("No readable backup found for store: {0}")]
+    NoBackup(String),
+
+    #[error("Store {0} was modified externally since it was last read")]
+    Conflict(String),
+
+    #[cfg(feature = "json")]
+    #[error("JSON parsing error: {0}")]
+    JsonParse(#[source] serde_json::Error),
+
+    #[cfg(feature = "json")]
+    #[error("JSON error: {0}")]
+    Json(#[source] serde_json::Error),
+
+    #[cfg(feature = "toml")]
+    #[error("TOML parsing error: {0}")]
+    TomlParse(#[source] toml::de::Error),
+
+    #[cfg(feature = "toml")]
+    #[error("TOML error: {0}")]
+    Toml(#[source] toml::ser::Error),
+
+    #[cfg(feature = "yaml")]
+    #[error("YAML parsing error: {0}")]
+    YamlParse(#[source] serde_yaml::Error),
+
+    #[cfg(feature = "yaml")]
+    #[error("YAML error: {0}")]
+    Yaml(#[source] serde_yaml::Error),
+
+    #[cfg(any(feature = "zstd", feature = "gzip"))]
+    #[error("Failed to compress store: {0}")]
+    Compression(#[source] std::io::Error),
+
+    #[cfg(any(feature = "zstd", feature = "gzip"))]
+    #[error("Failed to decompress store: {0}")]
+    Decompression(#[source] std::io::Error),
+
+    #[cfg(feature = "bincode")]
+    #[error("Bincode parsing error: {0}")]
+    BincodeParse(#[source] bincode::Error),
+
+    #[cfg(feature = "bincode")]
+    #[error("Bincode error: {0}")]
+    Bincode(#[source] bincode::Error),
+
+    #[cfg(feature = "msgpack")]
+    #[error("MessagePack parsing error: {0}")]
+    MessagePackParse(#[source] rmp_serde::decode::Error),
+
+    #[cfg(feature = "msgpack")]
+    #[error("MessagePack error: {0}")]
+    MessagePack(#[source] rmp_serde::encode::Error),
+
+    #[cfg(feature = "cbor")]
+    #[error("CBOR parsing error: {0}")]
+    CborParse(#[source] ciborium::de::Error<std::io::Error>),
+
+    #[cfg(feature = "cbor")]
+    #[error("CBOR error: {0}")]
+    Cbor(#[source] ciborium::ser::Error<std::io::Error>),
+
+    #[cfg(feature = "encryption")]
+    #[error("Failed to load encryption key: {0}")]
+    Key(#[source] std::io::Error),
+
+    #[cfg(feature = "encryption")]
+    #[error("Store is encrypted but no key provider is set")]
+    NoKeyProvider,
+
+    #[cfg(feature = "encryption")]
+    #[error("Store was encrypted with a different key")]
+    WrongKey,
+
+    #[cfg(feature = "encryption")]
+    #[error("Encrypted store was tampered with or is corrupted")]
+    Tampered,
+
+    #[cfg(feature = "encryption")]
+    #[error("Encrypted store was found in plaintext")]
+    Unencrypted,
+
+    #[cfg(feature = "encryption")]
+    #[error("Failed to encrypt store")]
+    Encryption,
+
+    #[error("Failed to migrate store to version {version}: {message}")]
+    Migration { version: u32, message: String },
+
+    #[error("Store version {version} is newer than the supported version {supported}")]
+    UnsupportedVersion { version: u32, supported: u32 },
+
+    #[error("Invalid store value at {path}: {message}")]
+    Validation { path: String, message: String },
+
+    #[error("Failed to roll back store after error: {error}, rollback error: {rollback}")]
+    Rollback {
+        /// The error which made the modification fail.
+        error: Box<StoreError>,
+        /// The error which kept the in-memory store from being restored. It no longer matches
+        /// the store on disk.
+        rollback: Box<StoreError>,
+    },
+
+    #[cfg(feature = "watcher")]
+    #[error("Failed to watch store: {0}")]
+    Watch(#[source] notify::Error),
+}
+
+impl StoreError {
+    /// Returns whether the contents of a store file could not be decrypted, decompressed or
+    /// parsed in its format. A wrong key is not a parse error, the file is fine.
+    pub(crate) fn is_parse(&self) -> bool {
+        match self {
+            StoreError::RonParse(_) => true,
+            #[cfg(feature = "encryption")]
+            StoreError::Tampered | StoreError::Unencrypted => true,
+            #[cfg(any(feature = "zstd", feature = "gzip"))]
+            StoreError::Decompression(_) => true,
+            #[cfg(feature = "json")]
+            StoreError::JsonParse(_) => true,
+            #[cfg(feature = "toml")]
+            StoreError::TomlParse(_) => true,
+            #[cfg(feature = "yaml")]
+            StoreError::YamlParse(_) => true,
+            #[cfg(feature = "bincode")]
+            StoreError::BincodeParse(_) => true,
+            #[cfg(feature = "msgpack")]
+            StoreError::MessagePackParse(_) => true,
+            #[cfg(feature = "cbor")]
+            StoreError::CborParse(_) => true,
+            _ => false,
+        }
+    }
 }
 
 #[derive(Debug, Default)]
@@ -42,10 +197,198 @@ pub enum StoringType {
     Config,
 }
 
+/// Controls how much effort `Storage` puts into making a successful write survive a crash or power loss.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Durability {
+    /// Leaves flushing to the operating system. This is the fastest level, but a power loss
+    /// shortly after a write may lose it.
+    #[default]
+    None,
+    /// Syncs the file contents to disk before the file replaces the previous version.
+    File,
+    /// Syncs the file and then its parent directory, so the replacement itself is persisted too.
+    FileAndDirectory,
+}
+
+/// Why a store was rejected by [`Storing::validate`]: the path of the offending field, such as
+/// `server.port`, and what is wrong with it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    pub path: String,
+    pub message: String,
+}
+
+impl ValidationError {
+    /// Creates an error for the field at `path`, described by `message`.
+    pub fn new(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl From<ValidationError> for StoreError {
+    fn from(err: ValidationError) -> Self {
+        StoreError::Validation {
+            path: err.path,
+            message: err.message,
+        }
+    }
+}
+
+/// What [`Storage::read`] does with a store file missing some fields of the store, such as a file
+/// written before a field was added.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MissingFields {
+    /// Returns the parse error, unless the missing fields have a `#[serde(default)]`.
+    #[default]
+    Fail,
+    /// Reads the file over `T::default()`, missing fields taking their default value. The file
+    /// is left as is until the store is next written.
+    Fill,
+    /// Fills the missing fields like [`MissingFields::Fill`], then rewrites the file with them.
+    FillAndRewrite,
+}
+
 pub trait Storing: Serialize + for<'de> Deserialize<'de> + Default {
     fn store_type() -> StoringType {
         StoringType::default()
     }
+
+    /// The format the store file is written in. RON by default.
+    fn format() -> Format {
+        Format::default()
+    }
+
+    /// The version of the store's schema, written in its file. Defaults to `0`, for stores which
+    /// are not versioned and are written as is.
+    ///
+    /// Versioned stores are written along with their version, such as
+    /// `(version: 2, store: (...))` in RON. Files of older versions, including the ones written
+    /// before the store was versioned, which are version `0`, are upgraded by the steps of
+    /// [`Storing::migrations`] when read. The upgraded store is written back, and the previous
+    /// file kept as `<store_id>.<extension>.v<version>.bak`. Migrating requires a format
+    /// describing its values, which rules out bincode.
+    const VERSION: u32 = 0;
+
+    /// The steps upgrading the files of older versions of the store, see [`Migrations`].
+    fn migrations() -> Migrations {
+        Migrations::new()
+    }
+
+    /// Overrides the compression of the `Storage` for this store.
+    ///
+    /// Returns `None` by default, which uses the compression configured with
+    /// [`Storage::with_compression`].
+    fn compression() -> Option<Compression> {
+        None
+    }
+
+    /// Whether the store file is encrypted with the key provider set with
+    /// [`Storage::with_key_provider`]. Returns `false` by default.
+    ///
+    /// Plain files of the store fail to read with [`StoreError::Unencrypted`], since anyone able
+    /// to write them could replace the store. Existing plain files are read, and encrypted on
+    /// their next save, only with [`Storage::with_plaintext_migration`].
+    #[cfg(feature = "encryption")]
+    fn encrypted() -> bool {
+        false
+    }
+
+    /// Whether writes patch the existing store file instead of replacing it, rewriting only the
+    /// values that changed. Returns `false` by default.
+    ///
+    /// Comments, ordering and formatting elsewhere in the file are kept, which suits
+    /// configuration stores edited by hand. Only [`Format::Ron`] and [`Format::Toml`] files are
+    /// patched, files in other formats or that cannot be patched safely are replaced as usual.
+    fn preserve_formatting() -> bool {
+        false
+    }
+
+    /// How the store is pretty printed when its format is [`Format::Ron`], both when its default
+    /// is created and when it is written.
+    ///
+    /// Returns `PrettyConfig::new().compact_arrays(true)` by default. Extensions such as
+    /// `implicit_some` are declared at the top of the file, so it can be read back as is. The
+    /// derive macro sets these options with the `#[storing(...)]` attribute:
+    ///
+    /// ```
+    /// # use rusty_store::Storing;
+    /// # use serde::{Deserialize, Serialize};
+    /// #[derive(Serialize, Deserialize, Default, Storing)]
+    /// #[storing(indentor = "\t", struct_names, compact_arrays = false, depth_limit = 3)]
+    /// #[storing(implicit_some, unwrap_newtypes)]
+    /// pub struct MyStore {
+    ///     pub name: Option<String>,
+    /// }
+    /// ```
+    fn pretty_config() -> PrettyConfig {
+        PrettyConfig::new().compact_arrays(true)
+    }
+
+    /// Overrides the durability level of the `Storage` for this store.
+    ///
+    /// Returns `None` by default, which uses the level configured with [`Storage::with_durability`].
+    fn durability() -> Option<Durability> {
+        None
+    }
+
+    /// Overrides the recovery policy of the `Storage` for this store.
+    ///
+    /// Returns `None` by default, which uses the policy configured with
+    /// [`Storage::with_recovery_policy`].
+    fn recovery_policy() -> Option<RecoveryPolicy> {
+        None
+    }
+
+    /// Checks the store after it is read and before it is written, rejecting values its type
+    /// allows but the application does not, such as a port of `0`. Accepts every store by
+    /// default.
+    ///
+    /// Rejected stores fail reads and writes with [`StoreError::Validation`], leaving the store
+    /// file untouched. The default store is validated too when it is written for a store that
+    /// does not exist yet, so `T::default()` must pass, or the store can never be created.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rusty_store::{Storing, ValidationError};
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Serialize, Deserialize)]
+    /// pub struct Server {
+    ///     pub port: u16,
+    /// }
+    ///
+    /// impl Default for Server {
+    ///     fn default() -> Self {
+    ///         Self { port: 8080 }
+    ///     }
+    /// }
+    ///
+    /// impl Storing for Server {
+    ///     fn validate(&self) -> Result<(), ValidationError> {
+    ///         if self.port < 1024 {
+    ///             return Err(ValidationError::new("port", "must be at least 1024"));
+    ///         }
+    ///         Ok(())
+    ///     }
+    /// }
+    /// ```
+    fn validate(&self) -> Result<(), ValidationError> {
+        Ok(())
+    }
+
+    /// Overrides what reads do with files missing some fields of the store.
+    ///
+    /// Returns `None` by default, which uses the behavior configured with
+    /// [`Storage::with_missing_fields`].
+    fn missing_fields() -> Option<MissingFields> {
+        None
+    }
 }
 
 /// `StoreHandle` acts as a container that holds store data in memory and provides methods to access
@@ -55,6 +398,8 @@ pub trait Storing: Serialize + for<'de> Deserialize<'de> + Default {
 pub struct StoreHandle<T> {
     store_id: String,
     store: T,
+    #[serde(skip)]
+    fingerprint: Option<Fingerprint>,
 }
 
 impl<T: Storing> StoreHandle<T> {
@@ -62,10 +407,11 @@ impl<T: Storing> StoreHandle<T> {
         Self {
             store: T::default(),
             store_id: store_id.to_owned(),
+            fingerprint: None,
         }
     }
 
-    fn set_store(&mut self, store: T) {
+    pub(crate) fn set_store(&mut self, store: T) {
         debug!("Setting store with id: {}", self.store_id);
         self.store = store;
     }
@@ -83,6 +429,15 @@ impl<T: Storing> StoreHandle<T> {
     pub fn store_id(&self) -> &str {
         &self.store_id
     }
+
+    /// Returns the fingerprint of the store file as it was last read or written by this handle.
+    pub(crate) fn fingerprint(&self) -> Option<Fingerprint> {
+        self.fingerprint
+    }
+
+    pub(crate) fn set_fingerprint(&mut self, fingerprint: Fingerprint) {
+        self.fingerprint = Some(fingerprint);
+    }
 }
 
 /// Handles file system paths for reading from and writing to data storage.
@@ -134,13 +489,32 @@ impl<T: Storing> StoreHandle<T> {
 /// let counter = handle.get_store();
 ///
 /// println!("Count: {}", counter.count);
-
 /// ```
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
 pub struct Storage {
     cache_dir: PathBuf,
     data_dir: PathBuf,
     config_dir: PathBuf,
+    #[serde(default)]
+    durability: Durability,
+    #[serde(default)]
+    lock_timeout: Option<Duration>,
+    #[serde(default)]
+    backups: usize,
+    #[serde(default)]
+    recovery_policy: RecoveryPolicy,
+    #[serde(default)]
+    compression: Compression,
+    #[serde(default)]
+    missing_fields: MissingFields,
+    #[serde(skip)]
+    on_recovery: Option<RecoveryCallback>,
+    #[cfg(feature = "encryption")]
+    #[serde(skip)]
+    key_provider: Option<KeyProviderRef>,
+    #[cfg(feature = "encryption")]
+    #[serde(default)]
+    plaintext_migration: bool,
 }
 
 impl Storage {
@@ -150,17 +524,17 @@ impl Storage {
     ///
     /// - Panics if the cache directory, data directory, or configuration directory path cannot be determined.
     pub fn new(app_id: &str) -> Self {
-        Self {
-            data_dir: dirs::data_dir()
+        Self::from_dirs(
+            dirs::cache_dir()
                 .expect("Failed to determine cache directory path")
                 .join(app_id),
-            config_dir: dirs::config_dir()
+            dirs::data_dir()
                 .expect("Failed to determine data directory path")
                 .join(app_id),
-            cache_dir: dirs::cache_dir()
+            dirs::config_dir()
                 .expect("Failed to determine configuration directory path")
                 .join(app_id),
-        }
+        )
     }
 
     /// Creates a new `Storage` instance with specific cache, data and config paths
@@ -169,9 +543,152 @@ impl Storage {
             cache_dir,
             data_dir,
             config_dir,
+            durability: Durability::default(),
+            lock_timeout: None,
+            backups: 0,
+            recovery_policy: RecoveryPolicy::default(),
+            compression: Compression::default(),
+            missing_fields: MissingFields::default(),
+            on_recovery: None,
+            #[cfg(feature = "encryption")]
+            key_provider: None,
+            #[cfg(feature = "encryption")]
+            plaintext_migration: false,
         }
     }
 
+    /// Sets the durability level used when writing stores.
+    ///
+    /// Stores can override this level through [`Storing::durability`], for example to always
+    /// sync configuration stores while letting caches use [`Durability::None`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rusty_store::{Durability, Storage};
+    ///
+    /// let storage = Storage::new("APP_ID").with_durability(Durability::File);
+    /// ```
+    pub fn with_durability(mut self, durability: Durability) -> Self {
+        self.durability = durability;
+        self
+    }
+
+    /// Sets how long reads and writes wait for another process to release a store's lock
+    /// before failing with [`StoreError::Lock`]. By default they wait indefinitely.
+    pub fn with_lock_timeout(mut self, timeout: Duration) -> Self {
+        self.lock_timeout = Some(timeout);
+        self
+    }
+
+    /// Keeps the last `count` versions of every store as backup files next to it.
+    ///
+    /// Before a write replaces a store file, the previous file becomes
+    /// `<store_id>.<extension>.1.bak`, and older backups are shifted up to
+    /// `<store_id>.<extension>.<count>.bak`. Backups can be listed with [`Storage::backups`] and
+    /// restored with [`Storage::restore_backup`] or [`Storage::restore_last_good`]. No backups
+    /// are kept by default. Lowering the count removes the backups beyond it on the next write.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rusty_store::Storage;
+    ///
+    /// let storage = Storage::new("APP_ID").with_backups(3);
+    /// ```
+    pub fn with_backups(mut self, count: usize) -> Self {
+        self.backups = count;
+        self
+    }
+
+    /// Sets the compression of the store files written from now on.
+    ///
+    /// Stores can override the compression through [`Storing::compression`]. Files are not
+    /// compressed by default, and are read whether they are compressed or not.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rusty_store::{Compression, Storage};
+    ///
+    /// # #[cfg(feature = "zstd")]
+    /// let storage = Storage::new("APP_ID").with_compression(Compression::Zstd);
+    /// ```
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Sets what reads do when a store file exists but cannot be parsed.
+    ///
+    /// Stores can override the policy through [`Storing::recovery_policy`].
+    /// By default, reads fail with the parse error.
+    pub fn with_recovery_policy(mut self, policy: RecoveryPolicy) -> Self {
+        self.recovery_policy = policy;
+        self
+    }
+
+    /// Sets what reads do when a store file lacks some fields of the store, which happens to
+    /// files written before the fields were added.
+    ///
+    /// Stores can override it through [`Storing::missing_fields`]. By default, reads fail with
+    /// the parse error unless the missing fields have a `#[serde(default)]`. Filling them
+    /// requires a format describing its values, which rules out bincode.
+    pub fn with_missing_fields(mut self, missing_fields: MissingFields) -> Self {
+        self.missing_fields = missing_fields;
+        self
+    }
+
+    /// Sets a callback invoked whenever a store is recovered according to its
+    /// [`RecoveryPolicy`], for example to tell the user their settings were reset.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rusty_store::{RecoveryPolicy, Storage};
+    ///
+    /// let storage = Storage::new("APP_ID")
+    ///     .with_recovery_policy(RecoveryPolicy::MoveAside)
+    ///     .on_recovery(|recovery| {
+    ///         eprintln!("Store {} was reset: {}", recovery.store_id(), recovery.error());
+    ///     });
+    /// ```
+    pub fn on_recovery<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&Recovery) + Send + Sync + 'static,
+    {
+        self.on_recovery = Some(RecoveryCallback(Arc::new(callback)));
+        self
+    }
+
+    /// Sets the key provider encrypting the stores whose [`Storing::encrypted`] returns `true`.
+    ///
+    /// Encrypted files are decrypted whenever they are read, whatever the store.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rusty_store::{KeyFile, Storage};
+    ///
+    /// let storage = Storage::new("APP_ID").with_key_provider(KeyFile::new("/path/to/store.key"));
+    /// ```
+    #[cfg(feature = "encryption")]
+    pub fn with_key_provider<P: KeyProvider + 'static>(mut self, provider: P) -> Self {
+        self.key_provider = Some(KeyProviderRef(Arc::new(provider)));
+        self
+    }
+
+    /// Lets encrypted stores read their files still in plaintext, which are encrypted on their
+    /// next save. Disabled by default.
+    ///
+    /// Only enable it while stores are migrated to encryption: meanwhile, anyone able to write a
+    /// store file can replace the store with plaintext of their own.
+    #[cfg(feature = "encryption")]
+    pub fn with_plaintext_migration(mut self, enabled: bool) -> Self {
+        self.plaintext_migration = enabled;
+        self
+    }
+
     /// Returns a new StoreManager of type `T` with the given `store_id`
     pub fn new_manager<T: Storing>(&self, store_id: &str) -> Result<StoreManager<T>, StoreError> {
         StoreManager::<T>::new(self, store_id)
@@ -184,69 +701,216 @@ impl Storage {
 
     /// Reads the store from a file and updates the provided `StoreHandle`.
     /// If the file does not exist, it creates a default store if a default is available.
+    /// If the file cannot be parsed, the store's [`RecoveryPolicy`] decides what happens.
+    ///
+    /// Store files are named `<store_id>.<extension>` after the [`Format`] of the store. A file
+    /// left without extension by previous versions, or written in another format, is converted
+    /// to the current name and format the first time the store is read.
+    ///
+    /// A shared lock is held on the store while reading, so other processes cannot write it
+    /// at the same time. Reads which have to write the store file, to create, convert, recover,
+    /// migrate or fill it, take an exclusive lock instead.
     ///
     /// # Example
     ///
     /// ```
-    /// let storage = Storage::new(app);
-    /// let handle: StoreHandle<MyStore> = StoreHandle::new("my_store_id");
+    /// # use rusty_store::{Storage, StoreHandle, Storing};
+    /// # use serde::{Deserialize, Serialize};
+    /// # #[derive(Serialize, Deserialize, Default, Storing)]
+    /// # pub struct MyStore {
+    /// #     pub count: u32,
+    /// # }
+    /// let storage = Storage::new("APP_ID");
+    /// let mut handle: StoreHandle<MyStore> = StoreHandle::new("my_store_id");
     ///
     /// storage.read(&mut handle).expect("Failed to read store");
     ///
     /// ```
     pub fn read<T: Storing>(&self, handle: &mut StoreHandle<T>) -> Result<(), StoreError> {
+        {
+            let _lock = self.lock::<T>(handle.store_id(), LockMode::Shared)?;
+            if self.read_shared(handle)? {
+                return Ok(());
+            }
+        }
+
+        // Another process may have fixed the file meanwhile, it is checked again.
+        debug!(
+            "Store with id: {} has to be written, reading it again exclusively",
+            handle.store_id()
+        );
+        let _lock = self.lock::<T>(handle.store_id(), LockMode::Exclusive)?;
+        self.read_unlocked(handle)
+    }
+
+    /// Reads the store without writing anything, as allowed by a shared lock. Returns `false`,
+    /// leaving the handle untouched, if reading it requires writing the store file.
+    fn read_shared<T: Storing>(&self, handle: &mut StoreHandle<T>) -> Result<bool, StoreError> {
+        let contents = match fs::read(self.store_path::<T>(handle.store_id())) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+            Err(err) => return Err(StoreError::Read(err)),
+        };
+
+        let parsed = match self.parse_migrated::<T>(&contents) {
+            Ok(parsed) => parsed,
+            Err(err) if err.is_parse() && self.recovery_policy::<T>() != RecoveryPolicy::Fail => {
+                return Ok(false)
+            }
+            Err(err) => return Err(err),
+        };
+        let rewrite = parsed.migrated_from.is_some()
+            || (parsed.filled && self.missing_fields::<T>() == MissingFields::FillAndRewrite);
+        if rewrite {
+            return Ok(false);
+        }
+
+        handle.set_store(parsed.store);
+        handle.set_fingerprint(Fingerprint::of(&contents));
+        info!("Successfully read store with id: {}", handle.store_id());
+        Ok(true)
+    }
+
+    /// Reads the store like [`Storage::read`], assuming the caller already holds its exclusive
+    /// lock.
+    pub(crate) fn read_unlocked<T: Storing>(
+        &self,
+        handle: &mut StoreHandle<T>,
+    ) -> Result<(), StoreError> {
         debug!("Reading store with id: {}", handle.store_id());
-        self.open_file::<T, _>(
+        self.migrate_legacy::<T>(handle.store_id())?;
+
+        let mut migrated_from = None;
+        let mut filled = false;
+        let result = self.open_file::<T, _>(
             |file, handle| {
-                let store = Self::read_string(file).map_err(StoreError::Read)?;
-                let store_data: T = ron::from_str(&store).map_err(StoreError::RonParse)?;
+                let store = Self::read_bytes(file).map_err(StoreError::Read)?;
+                let parsed = self.parse_migrated::<T>(&store)?;
 
-                handle.set_store(store_data);
+                handle.set_store(parsed.store);
+                handle.set_fingerprint(Fingerprint::of(&store));
+                migrated_from = parsed.migrated_from;
+                filled = parsed.filled;
 
                 info!("Successfully read store with id: {}", handle.store_id());
                 Ok(())
             },
             handle,
-        )
+        );
+
+        match result {
+            Err(err) if err.is_parse() => self.recover(handle, err),
+            Ok(()) => {
+                if let Some(version) = migrated_from {
+                    let fingerprint =
+                        self.rewrite_migrated(handle.store_id(), version, handle.get_store())?;
+                    handle.set_fingerprint(fingerprint);
+                } else if filled && self.missing_fields::<T>() == MissingFields::FillAndRewrite {
+                    let fingerprint = self.write_contents::<T>(
+                        handle.store_id(),
+                        &self.serialize(handle.get_store())?,
+                    )?;
+                    handle.set_fingerprint(fingerprint);
+                    info!(
+                        "Filled missing fields of store with id: {}",
+                        handle.store_id()
+                    );
+                }
+                Ok(())
+            }
+            result => result,
+        }
     }
 
     /// Writes the current store `T` from the provided `StoreHandle` to a file.
-    /// If the file does not exist, it creates a default store if a default is available.
+    /// If the file or its directory does not exist, they are created.
+    ///
+    /// The store is first written to a temporary file in the same directory, which is then
+    /// renamed over the previous file. Readers therefore see either the old or the new store,
+    /// never a partially written one. An exclusive lock is held on the store while writing.
     ///
     /// # Example
     ///
     /// ```
-    /// let storage = Storage::new(app);
-    /// let handle: StoreHandle<MyStore> = StoreHandle::new("my_store_id");
+    /// # use rusty_store::{Storage, StoreHandle, Storing};
+    /// # use serde::{Deserialize, Serialize};
+    /// # #[derive(Serialize, Deserialize, Default, Storing)]
+    /// # pub struct MyStore {
+    /// #     pub count: u32,
+    /// # }
+    /// let storage = Storage::new("APP_ID");
+    /// let mut handle: StoreHandle<MyStore> = StoreHandle::new("my_store_id");
     ///
-    /// storage.write(&mut handle).expect("Failed to read store");
+    /// storage.write(&mut handle).expect("Failed to write store");
     ///
     /// ```
     pub fn write<T: Storing>(&self, handle: &mut StoreHandle<T>) -> Result<(), StoreError> {
+        let _lock = self.lock::<T>(handle.store_id(), LockMode::Exclusive)?;
+        self.write_unlocked(handle)
+    }
+
+    /// Writes the store like [`Storage::write`], assuming the caller already holds its lock.
+    pub(crate) fn write_unlocked<T: Storing>(
+        &self,
+        handle: &mut StoreHandle<T>,
+    ) -> Result<(), StoreError> {
         debug!("Writing store with id: {}", handle.store_id());
-        self.open_file::<T, _>(
-            |file: &mut File, handle| {
-                let store = handle.get_store_mut();
 
-                let str =
-                    ron::ser::to_string_pretty(&store, PrettyConfig::new().compact_arrays(true))
-                        .map_err(StoreError::Ron)?;
+        let contents = self.serialize(handle.get_store())?;
+        let fingerprint = self.write_contents::<T>(handle.store_id(), &contents)?;
+        handle.set_fingerprint(fingerprint);
 
-                file.write(str.as_bytes()).map_err(StoreError::Write)?;
+        info!("Successfully wrote store with id: {}", handle.store_id());
+        Ok(())
+    }
 
-                info!("Successfully wrote store with id: {}", handle.store_id());
-                Ok(())
-            },
-            handle,
-        )
+    /// Replaces the file of the store `store_id` with already serialized `contents`, keeping
+    /// backups and honoring the durability level. Assumes the caller holds the store's lock.
+    pub(crate) fn write_contents<T: Storing>(
+        &self,
+        store_id: &str,
+        contents: &[u8],
+    ) -> Result<Fingerprint, StoreError> {
+        let path = self.store_path::<T>(store_id);
+        let patched = match T::preserve_formatting() {
+            true => Self::patch_file::<T>(&path, contents),
+            false => None,
+        };
+        let contents = patched.as_deref().unwrap_or(contents);
+
+        // Backups are only rotated once the new version is safely written, so a failed write
+        // leaves them as they were.
+        Self::write_atomic(&path, contents, self.durability::<T>(), || {
+            self.rotate_backups(&path)
+        })?;
+
+        Ok(Fingerprint::of(contents))
     }
 
-    /// Opens the file for reading or writing. If the file does not exist, it attempts
+    /// Patches the current file at `path` to hold `contents`, if it can be read and patched.
+    fn patch_file<T: Storing>(path: &Path, contents: &[u8]) -> Option<Vec<u8>> {
+        let current = match fs::read(path) {
+            Ok(current) => current,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return None,
+            Err(err) => {
+                warn!(
+                    "Failed to read store to patch at path: {:?}, error: {:?}",
+                    path, err
+                );
+                return None;
+            }
+        };
+
+        debug!("Patching store at path: {:?}", path);
+        crate::patch::patch(T::format(), &current, contents)
+    }
+
+    /// Opens the file for reading. If the file does not exist, it attempts
     /// to create a default store if a default is provided.
     ///
     /// # Example
     ///
-    /// ```rust
+    /// ```ignore
     /// storage.open_file::<MyStore, _>(|file, handle| {
     ///     // Perform file operations
     ///     Ok(())
@@ -261,18 +925,11 @@ impl Storage {
         T: Storing,
         F: FnMut(&mut File, &mut StoreHandle<T>) -> Result<(), StoreError>,
     {
-        let mut dir_path = self.dir_path::<T>();
-        dir_path.push(handle.store_id()); // i don't like this
+        let dir_path = self.store_path::<T>(handle.store_id());
 
         debug!("Opening file at path: {:?}", dir_path);
 
-        match OpenOptions::new()
-            .read(true)
-            .write(true)
-            .create(false)
-            .truncate(false)
-            .open(&dir_path)
-        {
+        match OpenOptions::new().read(true).open(&dir_path) {
             Ok(mut config) => {
                 debug!("File opened successfully at path: {:?}", dir_path);
                 operation(&mut config, handle)
@@ -295,23 +952,150 @@ impl Storage {
         }
     }
 
-    fn store_default<T: Storing>(&self, path: PathBuf) -> Result<(), StoreError> {
+    pub(crate) fn store_default<T: Storing>(
+        &self,
+        path: PathBuf,
+    ) -> Result<Fingerprint, StoreError> {
         debug!("Storing default configuration at path: {:?}", path);
-        if let Some(parent) = path.parent() {
-            fs::create_dir_all(parent).map_err(StoreError::CreateDir)?;
-            info!("Created directory for path: {:?}", parent);
-        }
 
-        let default_store = T::default();
-        let str = ron::ser::to_string_pretty(&default_store, PrettyConfig::new())
-            .map_err(StoreError::Ron)?;
-        fs::write(&path, str).map_err(StoreError::Write)?;
+        let contents = self.serialize(&T::default())?;
+        Self::write_atomic(&path, &contents, self.durability::<T>(), || Ok(()))?;
         info!("Default store written at path: {:?}", &path);
 
+        Ok(Fingerprint::of(&contents))
+    }
+
+    /// Replaces the file at `path` with `contents` without ever exposing a partially written file.
+    ///
+    /// The contents are written to a hidden temporary file next to `path`, which is then renamed
+    /// over it. If anything fails before the rename, the temporary file is removed and the
+    /// previous file is left untouched. `durability` decides what is synced along the way, and
+    /// `before_replace` runs once the temporary file is written, right before the rename.
+    fn write_atomic(
+        path: &Path,
+        contents: &[u8],
+        durability: Durability,
+        before_replace: impl FnOnce() -> Result<(), StoreError>,
+    ) -> Result<(), StoreError> {
+        let parent = path.parent().unwrap_or_else(|| Path::new("."));
+        fs::create_dir_all(parent).map_err(StoreError::CreateDir)?;
+
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+
+        // Temporary files are private to their owner. The store keeps the permissions of the
+        // file it replaces, and a new store gets the usual ones, filtered by the umask.
+        let prefix = format!(".{}.", file_name);
+        let mut builder = tempfile::Builder::new();
+        builder.prefix(&prefix).suffix(".tmp");
+        let previous = fs::metadata(path)
+            .ok()
+            .map(|metadata| metadata.permissions());
+        #[cfg(unix)]
+        if previous.is_none() {
+            builder.permissions(std::os::unix::fs::PermissionsExt::from_mode(0o666));
+        }
+        let mut file = builder.tempfile_in(parent).map_err(StoreError::Write)?;
+        if let Some(permissions) = previous {
+            file.as_file()
+                .set_permissions(permissions)
+                .map_err(StoreError::Write)?;
+        }
+        debug!("Writing to temporary file at path: {:?}", file.path());
+
+        file.write_all(contents).map_err(StoreError::Write)?;
+        if durability != Durability::None {
+            file.as_file().sync_all().map_err(StoreError::Sync)?;
+        }
+
+        before_replace()?;
+        file.persist(path)
+            .map_err(|err| StoreError::Write(err.error))?;
+
+        if durability == Durability::FileAndDirectory {
+            Self::sync_dir(parent)?;
+        }
+
+        debug!("Replaced file at path: {:?}", path);
+        Ok(())
+    }
+
+    /// Syncs the directory entry list, persisting files created or renamed inside it.
+    #[cfg(unix)]
+    fn sync_dir(dir: &Path) -> Result<(), StoreError> {
+        debug!("Syncing directory at path: {:?}", dir);
+        File::open(dir)
+            .and_then(|dir| dir.sync_all())
+            .map_err(StoreError::Sync)
+    }
+
+    /// Directories cannot be opened and synced on this platform, renames are persisted by the
+    /// file system itself.
+    #[cfg(not(unix))]
+    fn sync_dir(_dir: &Path) -> Result<(), StoreError> {
         Ok(())
     }
 
-    fn dir_path<T: Storing>(&self) -> PathBuf {
+    /// Acquires an advisory lock on the store `store_id`, shared between processes.
+    pub(crate) fn lock<T: Storing>(
+        &self,
+        store_id: &str,
+        mode: LockMode,
+    ) -> Result<StoreLock, StoreError> {
+        let path = self.dir_path::<T>().join(format!("{}.lock", store_id));
+        StoreLock::acquire(path, mode, self.lock_timeout)
+    }
+
+    /// Acquires an advisory lock on the store `store_id` like [`Storage::lock`], but gives up
+    /// and returns `None` as soon as `cancelled` returns `true` while waiting.
+    pub(crate) fn lock_unless<T: Storing>(
+        &self,
+        store_id: &str,
+        mode: LockMode,
+        cancelled: impl Fn() -> bool,
+    ) -> Result<Option<StoreLock>, StoreError> {
+        let path = self.dir_path::<T>().join(format!("{}.lock", store_id));
+        StoreLock::acquire_unless(path, mode, self.lock_timeout, cancelled)
+    }
+
+    pub(crate) fn backup_count(&self) -> usize {
+        self.backups
+    }
+
+    pub(crate) fn recovery_policy<T: Storing>(&self) -> RecoveryPolicy {
+        T::recovery_policy().unwrap_or(self.recovery_policy)
+    }
+
+    pub(crate) fn recovery_callback(&self) -> Option<&RecoveryCallback> {
+        self.on_recovery.as_ref()
+    }
+
+    fn durability<T: Storing>(&self) -> Durability {
+        T::durability().unwrap_or(self.durability)
+    }
+
+    fn compression<T: Storing>(&self) -> Compression {
+        T::compression().unwrap_or(self.compression)
+    }
+
+    pub(crate) fn missing_fields<T: Storing>(&self) -> MissingFields {
+        T::missing_fields().unwrap_or(self.missing_fields)
+    }
+
+    /// Returns the path of the file of the store `store_id`, named `<store_id>.<extension>` after
+    /// the format of the store.
+    pub(crate) fn store_path<T: Storing>(&self, store_id: &str) -> PathBuf {
+        self.format_path::<T>(store_id, T::format())
+    }
+
+    pub(crate) fn format_path<T: Storing>(&self, store_id: &str, format: Format) -> PathBuf {
+        self.dir_path::<T>()
+            .join(format!("{}.{}", store_id, format.extension()))
+    }
+
+    pub(crate) fn dir_path<T: Storing>(&self) -> PathBuf {
         let path = match T::store_type() {
             StoringType::Cache => self.cache_dir.clone(),
             StoringType::Data => self.data_dir.clone(),
@@ -325,10 +1109,164 @@ impl Storage {
         path
     }
 
-    fn read_string(mut file: &File) -> Result<String, std::io::Error> {
-        let mut buf = String::new();
-        file.read_to_string(&mut buf)?;
-        debug!("Read string from file, length: {}", buf.len());
+    /// Validates a store, then serializes it into the contents of a store file, in the format
+    /// and with the compression of the store, then encrypted if the store is.
+    pub(crate) fn serialize<T: Storing>(&self, store: &T) -> Result<Vec<u8>, StoreError> {
+        store.validate()?;
+        let contents = self
+            .compression::<T>()
+            .compress(migration::serialize(T::format(), store)?)?;
+
+        #[cfg(feature = "encryption")]
+        if T::encrypted() {
+            let provider = self
+                .key_provider
+                .as_ref()
+                .ok_or(StoreError::NoKeyProvider)?;
+            return provider.encrypt(&contents);
+        }
+
+        Ok(contents)
+    }
+
+    /// Deserializes a store from the contents of a store file, in the format of the store and
+    /// migrated if it has an older version, then validates it.
+    pub(crate) fn parse<T: Storing>(&self, contents: &[u8]) -> Result<T, StoreError> {
+        self.parse_migrated(contents).map(|parsed| parsed.store)
+    }
+
+    /// Deserializes a store like [`Storage::parse`], telling whether it was migrated.
+    pub(crate) fn parse_migrated<T: Storing>(
+        &self,
+        contents: &[u8],
+    ) -> Result<Parsed<T>, StoreError> {
+        let parsed = migration::parse::<T>(
+            T::format(),
+            &self.decode::<T>(contents, T::format())?,
+            self.missing_fields::<T>(),
+        )?;
+        parsed.store.validate()?;
+        Ok(parsed)
+    }
+
+    /// Decrypts and decompresses the contents of a store file in `format` if needed, leaving the
+    /// serialized store. Plain contents of encrypted stores are rejected unless they are being
+    /// migrated.
+    #[cfg_attr(
+        not(feature = "encryption"),
+        allow(clippy::extra_unused_type_parameters)
+    )]
+    pub(crate) fn decode<'a, T: Storing>(
+        &self,
+        contents: &'a [u8],
+        format: Format,
+    ) -> Result<Cow<'a, [u8]>, StoreError> {
+        #[cfg(feature = "encryption")]
+        let contents = match KeyProviderRef::decrypt(self.key_provider.as_ref(), contents)? {
+            Cow::Borrowed(_) if T::encrypted() && !self.plaintext_migration => {
+                return Err(StoreError::Unencrypted)
+            }
+            contents => contents,
+        };
+        #[cfg(not(feature = "encryption"))]
+        let contents = Cow::Borrowed(contents);
+
+        match Compression::decompress(&contents, format)? {
+            Cow::Borrowed(_) => Ok(contents),
+            Cow::Owned(decompressed) => Ok(Cow::Owned(decompressed)),
+        }
+    }
+
+    pub(crate) fn read_bytes(mut file: &File) -> Result<Vec<u8>, std::io::Error> {
+        let mut buf = Vec::new();
+        file.read_to_end(&mut buf)?;
+        debug!("Read bytes from file, length: {}", buf.len());
         Ok(buf)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Default)]
+    struct Plain;
+
+    impl Storing for Plain {}
+
+    #[derive(Serialize, Deserialize, Default)]
+    struct Synced;
+
+    impl Storing for Synced {
+        fn durability() -> Option<Durability> {
+            Some(Durability::FileAndDirectory)
+        }
+    }
+
+    fn storage(durability: Durability) -> Storage {
+        Storage::from_dirs(
+            PathBuf::from("cache"),
+            PathBuf::from("data"),
+            PathBuf::from("config"),
+        )
+        .with_durability(durability)
+    }
+
+    #[test]
+    fn stores_use_the_storage_durability_by_default() {
+        assert_eq!(
+            storage(Durability::File).durability::<Plain>(),
+            Durability::File
+        );
+    }
+
+    #[test]
+    fn store_durability_overrides_the_storage() {
+        assert_eq!(
+            storage(Durability::None).durability::<Synced>(),
+            Durability::FileAndDirectory
+        );
+    }
+
+    fn temporary_files(dir: &Path) -> Vec<PathBuf> {
+        fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "tmp"))
+            .collect()
+    }
+
+    #[test]
+    fn before_replace_runs_once_the_temporary_file_is_written() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("store.ron");
+        fs::write(&path, "old").unwrap();
+
+        Storage::write_atomic(&path, b"new", Durability::None, || {
+            assert_eq!(fs::read_to_string(&path).unwrap(), "old");
+            let temporary = temporary_files(dir.path());
+            assert_eq!(temporary.len(), 1);
+            assert_eq!(fs::read_to_string(&temporary[0]).unwrap(), "new");
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert!(temporary_files(dir.path()).is_empty());
+    }
+
+    #[test]
+    fn failed_before_replace_keeps_the_previous_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("store.ron");
+        fs::write(&path, "old").unwrap();
+
+        let result = Storage::write_atomic(&path, b"new", Durability::None, || {
+            Err(StoreError::Backup(std::io::Error::other("rotation failed")))
+        });
+
+        assert!(matches!(result, Err(StoreError::Backup(_))));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "old");
+        assert!(temporary_files(dir.path()).is_empty());
+    }
+}
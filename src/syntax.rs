@@ -0,0 +1,304 @@
+/// A value of a RON document, located by its byte range.
+pub(crate) struct Node {
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+    pub(crate) kind: Kind,
+}
+
+pub(crate) enum Kind {
+    /// A number, string, char, boolean, unit variant or any other value without children.
+    Leaf,
+    /// A list, tuple or tuple struct. `open` is the position of its opening bracket, after
+    /// the name if any.
+    Seq { open: usize, items: Vec<Item> },
+    /// A struct or map. `open` is the position of its opening bracket, after the name if any.
+    Fields { open: usize, items: Vec<Item> },
+}
+
+/// An element of a [`Kind::Seq`] or an entry of a [`Kind::Fields`].
+pub(crate) struct Item {
+    pub(crate) key: Option<(usize, usize)>,
+    pub(crate) value: Node,
+    /// Whether the item is followed by a comma.
+    pub(crate) comma: bool,
+    /// The end of the item, after its comma if any.
+    pub(crate) end: usize,
+}
+
+impl Item {
+    pub(crate) fn start(&self) -> usize {
+        self.key.map_or(self.value.start, |key| key.0)
+    }
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn starts_with(&self, prefix: &[u8]) -> bool {
+        self.bytes[self.pos..].starts_with(prefix)
+    }
+
+    fn expect(&mut self, byte: u8) -> Option<()> {
+        (self.peek()? == byte).then(|| self.pos += 1)
+    }
+
+    /// Skips whitespace and comments, block comments being nested as in RON.
+    fn skip_trivia(&mut self) -> Option<()> {
+        loop {
+            match self.peek() {
+                Some(byte) if byte.is_ascii_whitespace() => self.pos += 1,
+                Some(b'/') if self.starts_with(b"//") => {
+                    while !matches!(self.peek(), None | Some(b'\n')) {
+                        self.pos += 1;
+                    }
+                }
+                Some(b'/') if self.starts_with(b"/*") => {
+                    let mut depth = 0;
+                    loop {
+                        if self.starts_with(b"/*") {
+                            depth += 1;
+                            self.pos += 2;
+                        } else if self.starts_with(b"*/") {
+                            depth -= 1;
+                            self.pos += 2;
+                            if depth == 0 {
+                                break;
+                            }
+                        } else {
+                            self.peek()?;
+                            self.pos += 1;
+                        }
+                    }
+                }
+                _ => return Some(()),
+            }
+        }
+    }
+
+    /// Parses a whole document, skipping the `#![enable(...)]` attributes before its value.
+    fn document(&mut self) -> Option<Node> {
+        self.skip_trivia()?;
+        while self.starts_with(b"#!") {
+            while self.peek()? != b']' {
+                self.pos += 1;
+            }
+            self.pos += 1;
+            self.skip_trivia()?;
+        }
+        let node = self.value()?;
+        self.skip_trivia()?;
+        self.peek().is_none().then_some(node)
+    }
+
+    fn value(&mut self) -> Option<Node> {
+        self.skip_trivia()?;
+        let start = self.pos;
+        match self.peek()? {
+            b'[' => self.items(start, b']', false),
+            b'{' => self.items(start, b'}', true),
+            b'(' => self.parens(start),
+            b'"' | b'\'' => {
+                self.quoted()?;
+                self.leaf(start)
+            }
+            b'b' if matches!(self.bytes.get(self.pos + 1), Some(b'"' | b'\'')) => {
+                self.pos += 1;
+                self.quoted()?;
+                self.leaf(start)
+            }
+            b'r' | b'b' if self.raw_string()? => self.leaf(start),
+            byte if byte.is_ascii_digit() || matches!(byte, b'-' | b'+' | b'.') => {
+                self.number();
+                self.leaf(start)
+            }
+            _ => {
+                self.ident()?;
+                let end = self.pos;
+                self.skip_trivia()?;
+                if self.peek() == Some(b'(') {
+                    self.parens(start)
+                } else {
+                    self.pos = end;
+                    self.leaf(start)
+                }
+            }
+        }
+    }
+
+    fn leaf(&self, start: usize) -> Option<Node> {
+        Some(Node {
+            start,
+            end: self.pos,
+            kind: Kind::Leaf,
+        })
+    }
+
+    /// Parses a struct, a tuple or the contents of a named value, starting at `(`.
+    fn parens(&mut self, start: usize) -> Option<Node> {
+        let open = self.pos;
+        self.pos += 1;
+        self.skip_trivia()?;
+        let fields = self.ident().is_some() && {
+            self.skip_trivia()?;
+            self.peek() == Some(b':')
+        };
+        self.pos = open;
+        if fields {
+            self.items(start, b')', true)
+        } else {
+            self.items(start, b')', false)
+        }
+    }
+
+    /// Parses the comma separated items between the bracket at the current position and
+    /// `close`, keyed by a field name or map key when `keyed`.
+    fn items(&mut self, start: usize, close: u8, keyed: bool) -> Option<Node> {
+        let open = self.pos;
+        self.pos += 1;
+        let mut items = Vec::new();
+        loop {
+            self.skip_trivia()?;
+            if self.peek()? == close {
+                self.pos += 1;
+                break;
+            }
+
+            let key = if keyed {
+                let key = self.value()?;
+                self.skip_trivia()?;
+                self.expect(b':')?;
+                Some((key.start, key.end))
+            } else {
+                None
+            };
+            let value = self.value()?;
+            self.skip_trivia()?;
+            let comma = self.peek() == Some(b',');
+            let end = if comma {
+                self.pos += 1;
+                self.pos
+            } else {
+                value.end
+            };
+            items.push(Item {
+                key,
+                value,
+                comma,
+                end,
+            });
+            if !comma {
+                self.skip_trivia()?;
+                self.expect(close)?;
+                break;
+            }
+        }
+
+        let kind = if keyed {
+            Kind::Fields { open, items }
+        } else {
+            Kind::Seq { open, items }
+        };
+        Some(Node {
+            start,
+            end: self.pos,
+            kind,
+        })
+    }
+
+    /// Skips a string or char literal, starting at its quote.
+    fn quoted(&mut self) -> Option<()> {
+        let quote = self.peek()?;
+        self.pos += 1;
+        loop {
+            match self.peek()? {
+                b'\\' => self.pos += 2,
+                byte if byte == quote => {
+                    self.pos += 1;
+                    return Some(());
+                }
+                _ => self.pos += 1,
+            }
+        }
+    }
+
+    /// Skips a raw string such as `r#"..."#` if there is one at the current position.
+    fn raw_string(&mut self) -> Option<bool> {
+        let start = self.pos;
+        if self.peek() == Some(b'b') {
+            self.pos += 1;
+        }
+        if self.peek() != Some(b'r') {
+            self.pos = start;
+            return Some(false);
+        }
+        self.pos += 1;
+        let mut hashes = 0;
+        while self.peek() == Some(b'#') {
+            hashes += 1;
+            self.pos += 1;
+        }
+        if self.peek() != Some(b'"') {
+            self.pos = start;
+            return Some(false);
+        }
+        self.pos += 1;
+
+        let mut terminator = vec![b'"'];
+        terminator.resize(hashes + 1, b'#');
+        while !self.starts_with(&terminator) {
+            self.peek()?;
+            self.pos += 1;
+        }
+        self.pos += terminator.len();
+        Some(true)
+    }
+
+    fn number(&mut self) {
+        let start = self.pos;
+        while let Some(byte) = self.peek() {
+            let exponent_sign = matches!(byte, b'-' | b'+')
+                && (self.pos == start || matches!(self.bytes[self.pos - 1], b'e' | b'E'));
+            if byte.is_ascii_alphanumeric() || matches!(byte, b'_' | b'.') || exponent_sign {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Skips an identifier, raw identifiers included.
+    fn ident(&mut self) -> Option<()> {
+        let start = self.pos;
+        if self.starts_with(b"r#") {
+            self.pos += 2;
+        }
+        while let Some(byte) = self.peek() {
+            if byte.is_ascii_alphanumeric() || byte == b'_' {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        if self.pos == start || self.bytes[start].is_ascii_digit() {
+            self.pos = start;
+            return None;
+        }
+        Some(())
+    }
+}
+
+/// Parses a RON document, returning `None` if it is not valid RON.
+pub(crate) fn parse(src: &str) -> Option<Node> {
+    Parser {
+        bytes: src.as_bytes(),
+        pos: 0,
+    }
+    .document()
+}
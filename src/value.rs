@@ -0,0 +1,982 @@
+use std::fmt;
+
+use serde::de::value::{MapDeserializer, SeqDeserializer};
+use serde::de::{
+    self, DeserializeSeed, Deserializer, EnumAccess, IntoDeserializer, MapAccess, SeqAccess,
+    VariantAccess, Visitor,
+};
+use serde::ser::{
+    self, SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant, SerializeTuple,
+    SerializeTupleStruct, SerializeTupleVariant, Serializer,
+};
+use serde::{forward_to_deserialize_any, Deserialize, Serialize};
+
+use crate::format::Format;
+use crate::syntax::{self, Kind, Node};
+
+/// An untyped store, handed to the migration steps registered with [`Migrations`].
+///
+/// Unlike `ron::Value`, it keeps the names of enum variants, so a migrated store deserializes
+/// into its current type whatever it holds.
+///
+/// [`Migrations`]: crate::Migrations
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Value {
+    /// The unit value `()`, or `null` in formats such as JSON.
+    Unit,
+    Bool(bool),
+    Integer(i128),
+    Float(f64),
+    Char(char),
+    String(String),
+    Bytes(Vec<u8>),
+    Option(Option<Box<Value>>),
+    /// A list, such as `[1, 2]`.
+    Seq(Vec<Value>),
+    /// A tuple, such as `(1, 2)` in RON.
+    Tuple(Vec<Value>),
+    /// A map, such as `{"a": 1}`. Formats other than RON also write structs as maps keyed by
+    /// field name.
+    Map(Vec<(Value, Value)>),
+    /// The fields of a struct, such as `(width: 800)` in RON.
+    Struct(Vec<(String, Value)>),
+    /// A value written with a name, such as the unit variant `Dark`, holding [`Value::Unit`],
+    /// the variant `Custom(3)` or a struct written with its name, `Window(width: 800)`.
+    Named(String, Box<Value>),
+}
+
+impl Value {
+    /// Returns the field `field` of a struct, or the entry keyed by the string `field` of a map.
+    pub fn get(&self, field: &str) -> Option<&Value> {
+        match self.unnamed() {
+            Value::Struct(fields) => fields
+                .iter()
+                .find(|(name, _)| name == field)
+                .map(|(_, value)| value),
+            Value::Map(entries) => entries
+                .iter()
+                .find(|(key, _)| key.as_str() == Some(field))
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// Returns the field `field` of a struct, or the entry keyed by the string `field` of a map.
+    pub fn get_mut(&mut self, field: &str) -> Option<&mut Value> {
+        match self.unnamed_mut() {
+            Value::Struct(fields) => fields
+                .iter_mut()
+                .find(|(name, _)| name == field)
+                .map(|(_, value)| value),
+            Value::Map(entries) => entries
+                .iter_mut()
+                .find(|(key, _)| key.as_str() == Some(field))
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// Sets the field `field` of a struct or map, appending it if it is missing, and returns its
+    /// previous value. Does nothing if the value is neither a struct nor a map.
+    pub fn insert(&mut self, field: &str, value: Value) -> Option<Value> {
+        if let Some(current) = self.get_mut(field) {
+            return Some(std::mem::replace(current, value));
+        }
+        match self.unnamed_mut() {
+            Value::Struct(fields) => fields.push((field.to_owned(), value)),
+            Value::Map(entries) => entries.push((Value::String(field.to_owned()), value)),
+            _ => {}
+        }
+        None
+    }
+
+    /// Removes the field `field` of a struct or map and returns its value.
+    pub fn remove(&mut self, field: &str) -> Option<Value> {
+        match self.unnamed_mut() {
+            Value::Struct(fields) => {
+                let index = fields.iter().position(|(name, _)| name == field)?;
+                Some(fields.remove(index).1)
+            }
+            Value::Map(entries) => {
+                let index = entries
+                    .iter()
+                    .position(|(key, _)| key.as_str() == Some(field))?;
+                Some(entries.remove(index).1)
+            }
+            _ => None,
+        }
+    }
+
+    /// Renames the field `from` of a struct or map to `to`, keeping its position. Returns whether
+    /// the field was found.
+    pub fn rename(&mut self, from: &str, to: &str) -> bool {
+        match self.unnamed_mut() {
+            Value::Struct(fields) => match fields.iter_mut().find(|(name, _)| name == from) {
+                Some((name, _)) => {
+                    *name = to.to_owned();
+                    true
+                }
+                None => false,
+            },
+            Value::Map(entries) => {
+                match entries
+                    .iter_mut()
+                    .find(|(key, _)| key.as_str() == Some(from))
+                {
+                    Some((key, _)) => {
+                        *key = Value::String(to.to_owned());
+                        true
+                    }
+                    None => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the string held by the value, if any.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    /// Returns the integer held by the value, if any.
+    pub fn as_integer(&self) -> Option<i128> {
+        match self {
+            Value::Integer(integer) => Some(*integer),
+            _ => None,
+        }
+    }
+
+    /// Returns the number held by the value, if any, integers included.
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            Value::Float(float) => Some(*float),
+            Value::Integer(integer) => Some(*integer as f64),
+            _ => None,
+        }
+    }
+
+    /// Returns the boolean held by the value, if any.
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(bool) => Some(*bool),
+            _ => None,
+        }
+    }
+
+    /// Returns the value without its name if it is [`Value::Named`].
+    fn unnamed(&self) -> &Value {
+        match self {
+            Value::Named(_, value) => value.unnamed(),
+            value => value,
+        }
+    }
+
+    fn unnamed_mut(&mut self) -> &mut Value {
+        match self {
+            Value::Named(_, value) => value.unnamed_mut(),
+            value => value,
+        }
+    }
+
+    /// Adds the struct fields of `defaults` missing from the value, recursing into the structs
+    /// both hold. Returns whether any field was added.
+    ///
+    /// `defaults` must come from [`Value::from_store`], which tells structs apart from maps and
+    /// only names enum variants. Maps are left as they are, so entries deleted from a map do not
+    /// come back. Enums are only filled when the value holds the variant of `defaults`, written
+    /// with its name as in RON: the fields of one variant do not belong in another.
+    pub(crate) fn fill(&mut self, defaults: Value) -> bool {
+        if let Value::Named(variant, defaults) = defaults {
+            return match self {
+                Value::Named(name, value) if *name == variant => value.fill(*defaults),
+                // Another variant, or a variant written as a map by formats other than RON.
+                _ => false,
+            };
+        }
+        match (defaults, self.unnamed_mut()) {
+            (Value::Option(Some(defaults)), Value::Option(Some(value))) => value.fill(*defaults),
+            // Formats other than RON write structs as maps.
+            (Value::Struct(defaults), value @ (Value::Struct(_) | Value::Map(_))) => {
+                let mut filled = false;
+                for (field, default) in defaults {
+                    match value.get_mut(&field) {
+                        Some(value) => filled |= value.fill(default),
+                        None => {
+                            value.insert(&field, default);
+                            filled = true;
+                        }
+                    }
+                }
+                filled
+            }
+            _ => false,
+        }
+    }
+
+    /// Sorts the entries of every map the value holds, so values holding the same maps compare
+    /// equal whatever order their entries were serialized in, as with `HashMap`.
+    pub(crate) fn canonicalize(&mut self) {
+        match self {
+            Value::Option(Some(value)) | Value::Named(_, value) => value.canonicalize(),
+            Value::Seq(items) | Value::Tuple(items) => {
+                items.iter_mut().for_each(Value::canonicalize);
+            }
+            Value::Struct(fields) => {
+                fields
+                    .iter_mut()
+                    .for_each(|(_, value)| value.canonicalize());
+            }
+            Value::Map(entries) => {
+                for (key, value) in entries.iter_mut() {
+                    key.canonicalize();
+                    value.canonicalize();
+                }
+                entries.sort_by_cached_key(|(key, _)| format!("{:?}", key));
+            }
+            _ => {}
+        }
+    }
+
+    /// Converts a store into a value, keeping its structs apart from its maps whatever the
+    /// format of the store.
+    pub(crate) fn from_store<S: Serialize>(store: &S) -> Option<Value> {
+        store.serialize(ValueSerializer).ok()
+    }
+
+    /// Parses the contents of a store file in `format` without knowing the type of the store.
+    /// Returns `None` if they cannot be parsed, or if the format does not describe its values.
+    pub(crate) fn parse(format: Format, contents: &[u8]) -> Option<Value> {
+        match format {
+            // RON is parsed by hand, its own untyped parsing drops the names of enum variants.
+            Format::Ron => {
+                let src = std::str::from_utf8(contents).ok()?;
+                Self::from_node(src, &syntax::parse(src)?)
+            }
+            #[cfg(feature = "bincode")]
+            Format::Bincode => None,
+            #[allow(unreachable_patterns)]
+            format => format.parse(contents).ok(),
+        }
+    }
+
+    fn from_node(src: &str, node: &Node) -> Option<Value> {
+        let (open, value) = match &node.kind {
+            Kind::Leaf => return Self::from_leaf(&src[node.start..node.end]),
+            Kind::Seq { open, items } => {
+                let items = items
+                    .iter()
+                    .map(|item| Self::from_node(src, &item.value))
+                    .collect::<Option<Vec<_>>>()?;
+                let value = match src.as_bytes()[*open] {
+                    b'[' => Value::Seq(items),
+                    _ if items.is_empty() => Value::Unit,
+                    _ => Value::Tuple(items),
+                };
+                (*open, value)
+            }
+            Kind::Fields { open, items } => {
+                let value = if src.as_bytes()[*open] == b'{' {
+                    let entries = items
+                        .iter()
+                        .map(|item| {
+                            let (start, end) = item.key?;
+                            let key = &src[start..end];
+                            let key = Self::from_node(key, &syntax::parse(key)?)?;
+                            Some((key, Self::from_node(src, &item.value)?))
+                        })
+                        .collect::<Option<Vec<_>>>()?;
+                    Value::Map(entries)
+                } else {
+                    let fields = items
+                        .iter()
+                        .map(|item| {
+                            let (start, end) = item.key?;
+                            let name = src[start..end].trim_start_matches("r#").to_owned();
+                            Some((name, Self::from_node(src, &item.value)?))
+                        })
+                        .collect::<Option<Vec<_>>>()?;
+                    Value::Struct(fields)
+                };
+                (*open, value)
+            }
+        };
+
+        let name = src[node.start..open].trim().trim_start_matches("r#");
+        Some(match (name, value) {
+            ("", value) => value,
+            ("Some", Value::Tuple(mut items)) if items.len() == 1 => {
+                Value::Option(Some(Box::new(items.remove(0))))
+            }
+            (name, value) => Value::Named(name.to_owned(), Box::new(value)),
+        })
+    }
+
+    fn from_leaf(text: &str) -> Option<Value> {
+        match text {
+            "true" => return Some(Value::Bool(true)),
+            "false" => return Some(Value::Bool(false)),
+            "None" => return Some(Value::Option(None)),
+            "inf" | "NaN" => {}
+            ident if ident.starts_with(|c: char| c.is_alphabetic() || c == '_') => {
+                let name = ident.trim_start_matches("r#").to_owned();
+                return Some(Value::Named(name, Box::new(Value::Unit)));
+            }
+            _ => {}
+        }
+
+        Some(match ron::from_str(text).ok()? {
+            ron::Value::Number(ron::Number::Integer(integer)) => Value::Integer(integer.into()),
+            ron::Value::Number(ron::Number::Float(float)) => Value::Float(float.get()),
+            ron::Value::String(string) => Value::String(string),
+            ron::Value::Char(char) => Value::Char(char),
+            ron::Value::Bool(bool) => Value::Bool(bool),
+            _ => return None,
+        })
+    }
+}
+
+macro_rules! from {
+    ($($type:ty => $variant:ident),* $(,)?) => {
+        $(
+            impl From<$type> for Value {
+                fn from(value: $type) -> Self {
+                    Value::$variant(value.into())
+                }
+            }
+        )*
+    };
+}
+
+from! {
+    bool => Bool,
+    i8 => Integer,
+    i16 => Integer,
+    i32 => Integer,
+    i64 => Integer,
+    u8 => Integer,
+    u16 => Integer,
+    u32 => Integer,
+    u64 => Integer,
+    f32 => Float,
+    f64 => Float,
+    char => Char,
+    String => String,
+    &str => String,
+}
+
+impl<'de> IntoDeserializer<'de, ron::Error> for Value {
+    type Deserializer = Value;
+
+    fn into_deserializer(self) -> Value {
+        self
+    }
+}
+
+impl<'de> Deserializer<'de> for Value {
+    type Error = ron::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ron::Error> {
+        match self {
+            Value::Unit => visitor.visit_unit(),
+            Value::Bool(bool) => visitor.visit_bool(bool),
+            Value::Integer(integer) => match (i64::try_from(integer), u64::try_from(integer)) {
+                (Ok(integer), _) => visitor.visit_i64(integer),
+                (_, Ok(integer)) => visitor.visit_u64(integer),
+                _ => visitor.visit_i128(integer),
+            },
+            Value::Float(float) => visitor.visit_f64(float),
+            Value::Char(char) => visitor.visit_char(char),
+            Value::String(string) => visitor.visit_string(string),
+            Value::Bytes(bytes) => visitor.visit_byte_buf(bytes),
+            Value::Option(None) => visitor.visit_none(),
+            Value::Option(Some(value)) => visitor.visit_some(*value),
+            Value::Seq(items) | Value::Tuple(items) => {
+                let mut seq = SeqDeserializer::new(items.into_iter());
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            Value::Map(entries) => {
+                let mut map = MapDeserializer::new(entries.into_iter());
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+            Value::Struct(fields) => {
+                let entries = fields
+                    .into_iter()
+                    .map(|(name, value)| (Value::String(name), value));
+                let mut map = MapDeserializer::new(entries);
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+            Value::Named(_, value) => value.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ron::Error> {
+        match self {
+            Value::Option(None) | Value::Unit => visitor.visit_none(),
+            Value::Option(Some(value)) => visitor.visit_some(*value),
+            value => visitor.visit_some(value),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, ron::Error> {
+        match self {
+            Value::Named(_, value) => value.deserialize_newtype_struct(name, visitor),
+            Value::Tuple(mut items) if items.len() == 1 => {
+                visitor.visit_newtype_struct(items.remove(0))
+            }
+            value => visitor.visit_newtype_struct(value),
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ron::Error> {
+        let (variant, content) = match self {
+            Value::Named(variant, content) => (variant, *content),
+            Value::String(variant) => (variant, Value::Unit),
+            Value::Map(entries) if entries.len() == 1 => {
+                let Some((Value::String(variant), content)) = entries.into_iter().next() else {
+                    return Err(de::Error::custom("expected an enum variant name"));
+                };
+                (variant, content)
+            }
+            value => {
+                return Err(de::Error::custom(format!(
+                    "expected an enum variant, found {:?}",
+                    value
+                )))
+            }
+        };
+        visitor.visit_enum(Variant { variant, content })
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// An enum variant and its content, [`Value::Unit`] for unit variants.
+struct Variant {
+    variant: String,
+    content: Value,
+}
+
+impl<'de> EnumAccess<'de> for Variant {
+    type Error = ron::Error;
+    type Variant = Value;
+
+    fn variant_seed<S: DeserializeSeed<'de>>(
+        self,
+        seed: S,
+    ) -> Result<(S::Value, Value), ron::Error> {
+        let variant = seed.deserialize(IntoDeserializer::<ron::Error>::into_deserializer(
+            self.variant,
+        ))?;
+        Ok((variant, self.content))
+    }
+}
+
+impl<'de> VariantAccess<'de> for Value {
+    type Error = ron::Error;
+
+    fn unit_variant(self) -> Result<(), ron::Error> {
+        match self {
+            Value::Unit => Ok(()),
+            value => Err(de::Error::custom(format!(
+                "expected a unit variant, found {:?}",
+                value
+            ))),
+        }
+    }
+
+    fn newtype_variant_seed<S: DeserializeSeed<'de>>(
+        self,
+        seed: S,
+    ) -> Result<S::Value, ron::Error> {
+        match self {
+            Value::Tuple(mut items) if items.len() == 1 => seed.deserialize(items.remove(0)),
+            value => seed.deserialize(value),
+        }
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, ron::Error> {
+        self.deserialize_any(visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ron::Error> {
+        self.deserialize_any(visitor)
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("any value")
+    }
+
+    fn visit_bool<E>(self, value: bool) -> Result<Value, E> {
+        Ok(Value::Bool(value))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Value, E> {
+        Ok(Value::Integer(value.into()))
+    }
+
+    fn visit_i128<E>(self, value: i128) -> Result<Value, E> {
+        Ok(Value::Integer(value))
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Value, E> {
+        Ok(Value::Integer(value.into()))
+    }
+
+    fn visit_u128<E: de::Error>(self, value: u128) -> Result<Value, E> {
+        i128::try_from(value)
+            .map(Value::Integer)
+            .map_err(|_| E::custom("integer out of range"))
+    }
+
+    fn visit_f64<E>(self, value: f64) -> Result<Value, E> {
+        Ok(Value::Float(value))
+    }
+
+    fn visit_char<E>(self, value: char) -> Result<Value, E> {
+        Ok(Value::Char(value))
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Value, E> {
+        Ok(Value::String(value.to_owned()))
+    }
+
+    fn visit_string<E>(self, value: String) -> Result<Value, E> {
+        Ok(Value::String(value))
+    }
+
+    fn visit_bytes<E>(self, value: &[u8]) -> Result<Value, E> {
+        Ok(Value::Bytes(value.to_owned()))
+    }
+
+    fn visit_byte_buf<E>(self, value: Vec<u8>) -> Result<Value, E> {
+        Ok(Value::Bytes(value))
+    }
+
+    fn visit_none<E>(self) -> Result<Value, E> {
+        Ok(Value::Option(None))
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        Ok(Value::Option(Some(Box::new(value))))
+    }
+
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(Value::Unit)
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Value, D::Error> {
+        Value::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut items = Vec::new();
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(Value::Seq(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut entries = Vec::new();
+        while let Some(entry) = map.next_entry()? {
+            entries.push(entry);
+        }
+        Ok(Value::Map(entries))
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Value, A::Error> {
+        let (variant, content) = data.variant::<String>()?;
+        let content = content.newtype_variant()?;
+        Ok(Value::Named(variant, Box::new(content)))
+    }
+}
+
+/// Serializes a store into a [`Value`], with its structs as [`Value::Struct`] and the variants of
+/// its enums as [`Value::Named`], like RON files are parsed.
+struct ValueSerializer;
+
+impl ValueSerializer {
+    fn integer<I: TryInto<i128>>(integer: I) -> Result<Value, ron::Error> {
+        integer
+            .try_into()
+            .map(Value::Integer)
+            .map_err(|_| ser::Error::custom("integer out of range"))
+    }
+}
+
+impl Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = ron::Error;
+    type SerializeSeq = Items;
+    type SerializeTuple = Items;
+    type SerializeTupleStruct = Items;
+    type SerializeTupleVariant = Items;
+    type SerializeMap = Entries;
+    type SerializeStruct = Fields;
+    type SerializeStructVariant = Fields;
+
+    fn serialize_bool(self, v: bool) -> Result<Value, ron::Error> {
+        Ok(Value::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value, ron::Error> {
+        Self::integer(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value, ron::Error> {
+        Self::integer(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value, ron::Error> {
+        Self::integer(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value, ron::Error> {
+        Self::integer(v)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Value, ron::Error> {
+        Self::integer(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value, ron::Error> {
+        Self::integer(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value, ron::Error> {
+        Self::integer(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value, ron::Error> {
+        Self::integer(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value, ron::Error> {
+        Self::integer(v)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Value, ron::Error> {
+        Self::integer(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Value, ron::Error> {
+        Ok(Value::Float(v.into()))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Value, ron::Error> {
+        Ok(Value::Float(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value, ron::Error> {
+        Ok(Value::Char(v))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value, ron::Error> {
+        Ok(Value::String(v.to_owned()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value, ron::Error> {
+        Ok(Value::Bytes(v.to_owned()))
+    }
+
+    fn serialize_none(self) -> Result<Value, ron::Error> {
+        Ok(Value::Option(None))
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value, ron::Error> {
+        Ok(Value::Option(Some(Box::new(value.serialize(self)?))))
+    }
+
+    fn serialize_unit(self) -> Result<Value, ron::Error> {
+        Ok(Value::Unit)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, ron::Error> {
+        Ok(Value::Unit)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Value, ron::Error> {
+        Ok(Value::Named(variant.to_owned(), Box::new(Value::Unit)))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Value, ron::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Value, ron::Error> {
+        let content = Value::Tuple(vec![value.serialize(self)?]);
+        Ok(Value::Named(variant.to_owned(), Box::new(content)))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Items, ron::Error> {
+        Ok(Items::new(len.unwrap_or_default(), false, None))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Items, ron::Error> {
+        Ok(Items::new(len, true, None))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<Items, ron::Error> {
+        Ok(Items::new(len, true, None))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Items, ron::Error> {
+        Ok(Items::new(len, true, Some(variant)))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Entries, ron::Error> {
+        Ok(Entries {
+            entries: Vec::new(),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Fields, ron::Error> {
+        Ok(Fields {
+            fields: Vec::new(),
+            variant: None,
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Fields, ron::Error> {
+        Ok(Fields {
+            fields: Vec::new(),
+            variant: Some(variant),
+        })
+    }
+}
+
+/// Wraps `value` in the enum variant `variant`, if any.
+fn variant(variant: Option<&'static str>, value: Value) -> Value {
+    match variant {
+        Some(variant) => Value::Named(variant.to_owned(), Box::new(value)),
+        None => value,
+    }
+}
+
+/// The items of a list, a tuple or a tuple variant being serialized.
+struct Items {
+    items: Vec<Value>,
+    tuple: bool,
+    variant: Option<&'static str>,
+}
+
+impl Items {
+    fn new(len: usize, tuple: bool, variant: Option<&'static str>) -> Self {
+        Self {
+            items: Vec::with_capacity(len),
+            tuple,
+            variant,
+        }
+    }
+
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ron::Error> {
+        self.items.push(value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Value, ron::Error> {
+        let value = match self.tuple {
+            true => Value::Tuple(self.items),
+            false => Value::Seq(self.items),
+        };
+        Ok(variant(self.variant, value))
+    }
+}
+
+impl SerializeSeq for Items {
+    type Ok = Value;
+    type Error = ron::Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ron::Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, ron::Error> {
+        self.finish()
+    }
+}
+
+impl SerializeTuple for Items {
+    type Ok = Value;
+    type Error = ron::Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ron::Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, ron::Error> {
+        self.finish()
+    }
+}
+
+impl SerializeTupleStruct for Items {
+    type Ok = Value;
+    type Error = ron::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ron::Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, ron::Error> {
+        self.finish()
+    }
+}
+
+impl SerializeTupleVariant for Items {
+    type Ok = Value;
+    type Error = ron::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ron::Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, ron::Error> {
+        self.finish()
+    }
+}
+
+/// The entries of a map being serialized.
+struct Entries {
+    entries: Vec<(Value, Value)>,
+    key: Option<Value>,
+}
+
+impl SerializeMap for Entries {
+    type Ok = Value;
+    type Error = ron::Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), ron::Error> {
+        self.key = Some(key.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ron::Error> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| <ron::Error as ser::Error>::custom("map value without a key"))?;
+        self.entries.push((key, value.serialize(ValueSerializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, ron::Error> {
+        Ok(Value::Map(self.entries))
+    }
+}
+
+/// The fields of a struct or a struct variant being serialized.
+struct Fields {
+    fields: Vec<(String, Value)>,
+    variant: Option<&'static str>,
+}
+
+impl SerializeStruct for Fields {
+    type Ok = Value;
+    type Error = ron::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), ron::Error> {
+        self.fields
+            .push((key.to_owned(), value.serialize(ValueSerializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, ron::Error> {
+        Ok(variant(self.variant, Value::Struct(self.fields)))
+    }
+}
+
+impl SerializeStructVariant for Fields {
+    type Ok = Value;
+    type Error = ron::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), ron::Error> {
+        SerializeStruct::serialize_field(self, key, value)
+    }
+
+    fn end(self) -> Result<Value, ron::Error> {
+        SerializeStruct::end(self)
+    }
+}
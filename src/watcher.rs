@@ -0,0 +1,98 @@
+use std::path::PathBuf;
+use std::sync::PoisonError;
+
+use log::{debug, info, warn};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::shared::SharedStoreManager;
+use crate::storage::{StoreError, Storing};
+
+/// Watches the file of a store and reloads it in its `SharedStoreManager` when it is changed by
+/// someone else, returned by [`SharedStoreManager::watch`].
+///
+/// Watching stops when the `StoreWatcher` is dropped.
+#[derive(Debug)]
+pub struct StoreWatcher {
+    path: PathBuf,
+    _watcher: RecommendedWatcher,
+}
+
+impl StoreWatcher {
+    /// Returns the path of the watched store file.
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+}
+
+impl<T: Storing + Send + Sync + 'static> SharedStoreManager<T> {
+    /// Watches the store file and reloads the store whenever it is changed by another process or
+    /// by hand, notifying the observers of the manager.
+    ///
+    /// Writes made through this manager are recognized and ignored. If the changed file cannot be
+    /// parsed, the last good store is kept until the file is fixed. The reload happens like
+    /// [`StoreManager::reload_if_changed`](crate::StoreManager::reload_if_changed), from a
+    /// background thread, so observers must not wait for the manager.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rusty_store::{SharedStoreManager, Storage, Storing};
+    /// # use serde::{Deserialize, Serialize};
+    /// # #[derive(Serialize, Deserialize, Default, Storing)]
+    /// # pub struct MyStore {
+    /// #     pub count: u32,
+    /// # }
+    /// let storage = Storage::new("APP_ID");
+    /// let manager = SharedStoreManager::<MyStore>::new(&storage, "my_store_id")
+    ///        .expect("Failed to create SharedStoreManager");
+    ///
+    /// manager.lock().subscribe(|_, new| println!("Count: {}", new.count));
+    /// let _watcher = manager.watch().expect("Failed to watch store");
+    /// ```
+    pub fn watch(&self) -> Result<StoreWatcher, StoreError> {
+        let path = self.lock().store_path();
+        let manager = self.downgrade();
+
+        let watched = path.clone();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            let event = match event {
+                Ok(event) => event,
+                Err(err) => {
+                    warn!("Failed to watch store at path: {:?}, error: {:?}", watched, err);
+                    return;
+                }
+            };
+            if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+                || !event.paths.contains(&watched)
+            {
+                return;
+            }
+            let Some(manager) = manager.upgrade() else {
+                return;
+            };
+
+            let mut manager = manager.write().unwrap_or_else(PoisonError::into_inner);
+            match manager.reload_if_changed() {
+                Ok(true) => info!("Reloaded store changed at path: {:?}", watched),
+                Ok(false) => debug!("Store at path: {:?} is unchanged", watched),
+                Err(err) => warn!(
+                    "Failed to reload store at path: {:?}, keeping the last good state, error: {:?}",
+                    watched, err
+                ),
+            }
+        })
+        .map_err(StoreError::Watch)?;
+
+        // Writes replace the file, so its directory is watched rather than the file itself.
+        let dir = path.parent().unwrap_or(&path);
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .map_err(StoreError::Watch)?;
+
+        debug!("Watching store at path: {:?}", path);
+        Ok(StoreWatcher {
+            path,
+            _watcher: watcher,
+        })
+    }
+}
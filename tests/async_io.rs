@@ -0,0 +1,118 @@
+#![cfg(feature = "tokio")]
+
+mod common;
+
+use std::fs;
+
+use rusty_store::{AsyncStoreManager, ModifyError, StoreError, StoreHandle, StoreManager, Storing};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Storing)]
+pub struct Counter {
+    pub count: u32,
+}
+
+#[tokio::test]
+async fn write_async_then_read_async() {
+    let (dir, storage) = common::storage();
+    let mut handle = StoreHandle::<Counter>::new("counter");
+    handle.get_store_mut().count = 9;
+
+    storage.write_async(&mut handle).await.unwrap();
+
+    let mut read = StoreHandle::<Counter>::new("counter");
+    storage.read_async(&mut read).await.unwrap();
+    assert_eq!(read.get_store().count, 9);
+    assert_eq!(
+        common::entries(&dir.path().join("data")),
+        vec!["counter.ron".to_owned()]
+    );
+}
+
+#[tokio::test]
+async fn write_async_is_seen_by_conflict_detection() {
+    let (_dir, storage) = common::storage();
+    let mut handle = StoreHandle::<Counter>::new("counter");
+    storage.write_async(&mut handle).await.unwrap();
+
+    handle.get_store_mut().count = 1;
+    storage.write_if_unchanged(&mut handle).unwrap();
+
+    let mut stale = StoreHandle::<Counter>::new("counter");
+    storage.read_async(&mut stale).await.unwrap();
+    handle.get_store_mut().count = 2;
+    storage.write_if_unchanged(&mut handle).unwrap();
+
+    stale.get_store_mut().count = 3;
+    let result = storage.write_if_unchanged(&mut stale);
+    assert!(matches!(result, Err(StoreError::Conflict(_))));
+}
+
+#[tokio::test]
+async fn failed_read_async_keeps_handle() {
+    let (dir, storage) = common::storage();
+    let mut handle = StoreHandle::<Counter>::new("counter");
+    handle.get_store_mut().count = 4;
+
+    fs::create_dir_all(dir.path().join("data")).unwrap();
+    fs::write(dir.path().join("data/counter.ron"), "not ron").unwrap();
+
+    assert!(storage.read_async(&mut handle).await.is_err());
+    assert_eq!(handle.get_store().count, 4);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_async_modifications() {
+    let (_dir, storage) = common::storage();
+    let manager = AsyncStoreManager::<Counter>::new(&storage, "counter")
+        .await
+        .unwrap();
+
+    let tasks: Vec<_> = (0..16)
+        .map(|_| {
+            let manager = manager.clone();
+            tokio::spawn(async move {
+                for _ in 0..5 {
+                    manager
+                        .modify_store(|store| store.count += 1)
+                        .await
+                        .unwrap();
+                }
+            })
+        })
+        .collect();
+    for task in tasks {
+        task.await.unwrap();
+    }
+
+    assert_eq!(manager.read().await.count, 80);
+    let reloaded = StoreManager::<Counter>::new(&storage, "counter").unwrap();
+    assert_eq!(reloaded.get_store().count, 80);
+}
+
+#[tokio::test]
+async fn async_manager_reloads_and_rejects() {
+    let (_dir, storage) = common::storage();
+    let manager = AsyncStoreManager::<Counter>::new(&storage, "counter")
+        .await
+        .unwrap();
+    let mut other = StoreManager::<Counter>::new(&storage, "counter").unwrap();
+    other.modify_store(|store| store.count = 6).unwrap();
+
+    assert_eq!(manager.get_store_alive().await.unwrap().count, 6);
+
+    let result = manager
+        .try_modify_store(|store| {
+            store.count = 100;
+            Err::<(), _>("too large")
+        })
+        .await;
+    assert!(matches!(result, Err(ModifyError::Rejected("too large"))));
+    assert_eq!(manager.read().await.count, 6);
+
+    manager
+        .with_manager(|manager| manager.get_store_mut().count = 7)
+        .await;
+    manager.save().await.unwrap();
+    assert_eq!(other.get_store_alive().unwrap().count, 7);
+}
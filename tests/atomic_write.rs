@@ -0,0 +1,236 @@
+mod common;
+
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use rusty_store::{StoreError, StoreHandle, Storing};
+use serde::{Deserialize, Serialize, Serializer};
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Storing)]
+pub struct Numbers {
+    pub values: Vec<u32>,
+}
+
+/// A store that refuses to serialize once `fail` is set, interrupting the write midway.
+#[derive(Deserialize, Default, Storing)]
+pub struct Flaky {
+    pub value: u32,
+    #[serde(skip)]
+    pub fail: bool,
+}
+
+impl Serialize for Flaky {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::{Error, SerializeStruct};
+
+        let mut state = serializer.serialize_struct("Flaky", 1)?;
+        state.serialize_field("value", &self.value)?;
+        if self.fail {
+            return Err(S::Error::custom("interrupted"));
+        }
+        state.end()
+    }
+}
+
+#[test]
+fn shorter_store_leaves_no_stale_bytes() {
+    let (dir, storage) = common::storage();
+    let mut handle = StoreHandle::<Numbers>::new("numbers");
+
+    handle.get_store_mut().values = (0..1000).collect();
+    storage.write(&mut handle).unwrap();
+
+    handle.get_store_mut().values = vec![1];
+    storage.write(&mut handle).unwrap();
+
+    let contents = fs::read_to_string(dir.path().join("data/numbers.ron")).unwrap();
+    let on_disk: Numbers = ron::from_str(&contents).unwrap();
+    assert_eq!(on_disk.values, vec![1]);
+
+    let mut reread = StoreHandle::<Numbers>::new("numbers");
+    storage.read(&mut reread).unwrap();
+    assert_eq!(reread.get_store(), handle.get_store());
+}
+
+#[test]
+fn failed_write_keeps_previous_store() {
+    let (dir, storage) = common::storage();
+    let mut handle = StoreHandle::<Flaky>::new("flaky");
+
+    handle.get_store_mut().value = 1;
+    storage.write(&mut handle).unwrap();
+    let before = fs::read_to_string(dir.path().join("data/flaky.ron")).unwrap();
+
+    handle.get_store_mut().value = 2;
+    handle.get_store_mut().fail = true;
+    assert!(matches!(
+        storage.write(&mut handle),
+        Err(StoreError::Ron(_))
+    ));
+
+    assert_eq!(
+        fs::read_to_string(dir.path().join("data/flaky.ron")).unwrap(),
+        before
+    );
+    assert_eq!(common::entries(&dir.path().join("data")), vec!["flaky.ron"]);
+}
+
+#[test]
+fn failed_replacement_removes_the_temporary_file() {
+    let (dir, storage) = common::storage();
+    // A directory in place of the store file lets the temporary file be written, then keeps it
+    // from being renamed over the store.
+    let path = dir.path().join("data/numbers.ron");
+    fs::create_dir_all(&path).unwrap();
+    fs::write(path.join("kept"), "contents").unwrap();
+
+    let mut handle = StoreHandle::<Numbers>::new("numbers");
+    handle.get_store_mut().values = vec![1, 2, 3];
+    assert!(matches!(
+        storage.write(&mut handle),
+        Err(StoreError::Write(_))
+    ));
+
+    assert_eq!(fs::read_to_string(path.join("kept")).unwrap(), "contents");
+    assert_eq!(
+        common::entries(&dir.path().join("data")),
+        vec!["numbers.ron"]
+    );
+}
+
+#[test]
+fn leftover_temporary_file_is_ignored() {
+    let (dir, storage) = common::storage();
+    let mut handle = StoreHandle::<Numbers>::new("numbers");
+
+    handle.get_store_mut().values = vec![1, 2, 3];
+    storage.write(&mut handle).unwrap();
+
+    // A writer that crashed before renaming leaves a truncated temporary file behind.
+    fs::write(
+        dir.path().join("data/.numbers.crashed.tmp"),
+        "(values: [4, 5",
+    )
+    .unwrap();
+
+    let mut reread = StoreHandle::<Numbers>::new("numbers");
+    storage.read(&mut reread).unwrap();
+    assert_eq!(reread.get_store().values, vec![1, 2, 3]);
+
+    storage.write(&mut handle).unwrap();
+    storage.read(&mut reread).unwrap();
+    assert_eq!(reread.get_store().values, vec![1, 2, 3]);
+}
+
+#[test]
+fn default_store_is_written_atomically() {
+    let (dir, storage) = common::storage();
+    let mut handle = StoreHandle::<Numbers>::new("numbers");
+
+    storage.read(&mut handle).unwrap();
+
+    assert_eq!(handle.get_store(), &Numbers::default());
+    assert_eq!(
+        common::entries(&dir.path().join("data")),
+        vec!["numbers.ron"]
+    );
+}
+
+#[test]
+fn readers_never_observe_partial_writes() {
+    let (_dir, storage) = common::storage();
+    let mut handle = StoreHandle::<Numbers>::new("numbers");
+    storage.write(&mut handle).unwrap();
+
+    let done = Arc::new(AtomicBool::new(false));
+    let reader = {
+        let storage = storage.clone();
+        let done = done.clone();
+        thread::spawn(move || {
+            let mut handle = StoreHandle::<Numbers>::new("numbers");
+            while !done.load(Ordering::Relaxed) {
+                storage
+                    .read(&mut handle)
+                    .expect("Reader observed a partially written store");
+            }
+        })
+    };
+
+    for i in 0..200 {
+        let len = if i % 2 == 0 { 5000 } else { 1 };
+        handle.get_store_mut().values = (0..len).collect();
+        storage.write(&mut handle).unwrap();
+    }
+
+    done.store(true, Ordering::Relaxed);
+    reader.join().unwrap();
+}
+
+#[cfg(unix)]
+mod permissions {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn mode(path: &std::path::Path) -> u32 {
+        fs::metadata(path).unwrap().permissions().mode() & 0o777
+    }
+
+    #[test]
+    fn writes_keep_the_permissions_of_the_store() {
+        let (dir, storage) = common::storage();
+        let mut handle = StoreHandle::<Numbers>::new("numbers");
+        storage.write(&mut handle).unwrap();
+        let path = dir.path().join("data/numbers.ron");
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o664)).unwrap();
+
+        handle.get_store_mut().values.push(1);
+        storage.write(&mut handle).unwrap();
+
+        assert_eq!(mode(&path), 0o664);
+    }
+
+    #[test]
+    fn failed_temporary_write_keeps_previous_store() {
+        let (dir, storage) = common::storage();
+        let mut handle = StoreHandle::<Numbers>::new("numbers");
+        handle.get_store_mut().values = vec![1];
+        storage.write(&mut handle).unwrap();
+        let data = dir.path().join("data");
+        let before = fs::read_to_string(data.join("numbers.ron")).unwrap();
+
+        fs::set_permissions(&data, fs::Permissions::from_mode(0o555)).unwrap();
+        // Privileged users write to read-only directories anyway.
+        let probe = data.join("probe");
+        if fs::write(&probe, "").is_ok() {
+            fs::remove_file(&probe).unwrap();
+            fs::set_permissions(&data, fs::Permissions::from_mode(0o755)).unwrap();
+            return;
+        }
+
+        handle.get_store_mut().values = vec![2];
+        let result = storage.write(&mut handle);
+        fs::set_permissions(&data, fs::Permissions::from_mode(0o755)).unwrap();
+
+        assert!(matches!(result, Err(StoreError::Write(_))), "{:?}", result);
+        assert_eq!(
+            fs::read_to_string(data.join("numbers.ron")).unwrap(),
+            before
+        );
+        assert_eq!(common::entries(&data), vec!["numbers.ron"]);
+    }
+
+    #[test]
+    fn new_stores_get_the_default_permissions() {
+        let (dir, storage) = common::storage();
+        // Files created by the standard library get the default permissions under the umask.
+        let reference = dir.path().join("reference");
+        fs::File::create(&reference).unwrap();
+
+        let mut handle = StoreHandle::<Numbers>::new("numbers");
+        storage.write(&mut handle).unwrap();
+
+        assert_eq!(mode(&dir.path().join("data/numbers.ron")), mode(&reference));
+    }
+}
@@ -0,0 +1,160 @@
+mod common;
+
+use std::fs;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use rusty_store::{StoreError, StoreManager, Storing};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default, Debug, Storing)]
+pub struct Geometry {
+    pub width: u32,
+}
+
+const DELAY: Duration = Duration::from_millis(100);
+
+fn width_on_disk(dir: &tempfile::TempDir) -> u32 {
+    let contents = fs::read_to_string(dir.path().join("data/geometry.ron")).unwrap();
+    ron::from_str::<Geometry>(&contents).unwrap().width
+}
+
+#[test]
+fn coalesces_modifications_into_one_write() {
+    let (dir, storage) = common::storage();
+    // Every write turns the previous version into a backup, so backups count the writes.
+    let storage = storage.with_backups(100);
+    let mut manager = StoreManager::<Geometry>::new(&storage, "geometry").unwrap();
+    manager.enable_autosave(DELAY);
+
+    for width in 1..=50 {
+        manager.modify_store(|store| store.width = width).unwrap();
+    }
+    assert_eq!(width_on_disk(&dir), 0);
+
+    thread::sleep(DELAY * 5);
+
+    assert_eq!(width_on_disk(&dir), 50);
+    assert_eq!(storage.backups::<Geometry>("geometry").unwrap().len(), 1);
+}
+
+#[test]
+fn flush_writes_right_away() {
+    let (dir, storage) = common::storage();
+    let mut manager = StoreManager::<Geometry>::new(&storage, "geometry").unwrap();
+    manager.enable_autosave(Duration::from_secs(60));
+
+    manager.modify_store(|store| store.width = 640).unwrap();
+    manager.flush().unwrap();
+
+    assert_eq!(width_on_disk(&dir), 640);
+}
+
+#[test]
+fn dropping_the_manager_flushes() {
+    let (dir, storage) = common::storage();
+    let mut manager = StoreManager::<Geometry>::new(&storage, "geometry").unwrap();
+    manager.enable_autosave(Duration::from_secs(60));
+
+    {
+        let mut store = manager.edit();
+        store.width = 800;
+    }
+    drop(manager);
+
+    assert_eq!(width_on_disk(&dir), 800);
+}
+
+#[test]
+fn disabling_autosave_flushes() {
+    let (dir, storage) = common::storage();
+    let mut manager = StoreManager::<Geometry>::new(&storage, "geometry").unwrap();
+    manager.enable_autosave(Duration::from_secs(60));
+
+    manager.modify_store(|store| store.width = 1024).unwrap();
+    manager.disable_autosave().unwrap();
+
+    assert!(!manager.is_autosave_enabled());
+    assert_eq!(width_on_disk(&dir), 1024);
+}
+
+#[test]
+fn background_errors_are_reported_by_flush() {
+    let (dir, storage) = common::storage();
+    let mut manager = StoreManager::<Geometry>::new(&storage, "geometry").unwrap();
+    manager.enable_autosave(DELAY);
+
+    fs::write(dir.path().join("data/geometry.ron"), "(width: 1)").unwrap();
+    manager.modify_store(|store| store.width = 2).unwrap();
+    thread::sleep(DELAY * 5);
+
+    assert!(matches!(manager.flush(), Err(StoreError::Conflict(_))));
+    assert_eq!(width_on_disk(&dir), 1);
+}
+
+#[test]
+fn direct_saves_supersede_pending_modifications() {
+    let (dir, storage) = common::storage();
+    let mut manager = StoreManager::<Geometry>::new(&storage, "geometry").unwrap();
+    manager.enable_autosave(DELAY);
+
+    manager.modify_store(|store| store.width = 1).unwrap();
+    manager.get_store_mut().width = 2;
+    manager.save().unwrap();
+    assert_eq!(width_on_disk(&dir), 2);
+
+    thread::sleep(DELAY * 5);
+    assert_eq!(width_on_disk(&dir), 2);
+
+    manager.modify_store(|store| store.width = 3).unwrap();
+    manager.flush().unwrap();
+    assert_eq!(manager.get_store_alive().unwrap().width, 3);
+}
+
+/// Runs `operation` on another thread and fails if it does not return within a few seconds.
+fn within_timeout(operation: impl FnOnce() + Send + 'static) {
+    let (done, finished) = mpsc::channel();
+    thread::spawn(move || {
+        operation();
+        done.send(()).unwrap();
+    });
+    finished
+        .recv_timeout(Duration::from_secs(5))
+        .expect("Operation did not return, the autosave worker is deadlocked");
+}
+
+#[test]
+fn disabling_autosave_while_locked_does_not_deadlock() {
+    let (dir, storage) = common::storage();
+    let mut manager = StoreManager::<Geometry>::new(&storage, "geometry").unwrap();
+    manager.enable_autosave(DELAY / 2);
+
+    within_timeout(move || {
+        let mut locked = manager.lock_exclusive().unwrap();
+        locked.modify_store(|store| store.width = 320).unwrap();
+        // Lets the worker start waiting on the lock held here.
+        thread::sleep(DELAY * 3);
+        locked.disable_autosave().unwrap();
+    });
+
+    assert_eq!(width_on_disk(&dir), 320);
+}
+
+#[test]
+fn reenabling_autosave_while_locked_does_not_deadlock() {
+    let (dir, storage) = common::storage();
+    let mut manager = StoreManager::<Geometry>::new(&storage, "geometry").unwrap();
+    manager.enable_autosave(DELAY / 2);
+
+    within_timeout(move || {
+        let mut locked = manager.lock_exclusive().unwrap();
+        locked.modify_store(|store| store.width = 480).unwrap();
+        thread::sleep(DELAY * 3);
+        locked.enable_autosave(Duration::from_secs(60));
+        locked.modify_store(|store| store.width = 600).unwrap();
+        locked.flush().unwrap();
+    });
+
+    assert_eq!(width_on_disk(&dir), 600);
+}
@@ -0,0 +1,122 @@
+mod common;
+
+use std::fs;
+
+use rusty_store::{StoreError, StoreHandle, StoreManager, Storing};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Storing)]
+pub struct Counter {
+    pub count: u32,
+}
+
+fn write_counts(storage: &rusty_store::Storage, counts: &[u32]) -> StoreHandle<Counter> {
+    let mut handle = StoreHandle::<Counter>::new("counter");
+    for count in counts {
+        handle.get_store_mut().count = *count;
+        storage.write(&mut handle).unwrap();
+    }
+    handle
+}
+
+#[test]
+fn keeps_the_last_versions() {
+    let (_dir, storage) = common::storage();
+    let storage = storage.with_backups(2);
+
+    write_counts(&storage, &[1, 2, 3, 4]);
+
+    let backups = storage.backups::<Counter>("counter").unwrap();
+    let counts: Vec<u32> = backups
+        .iter()
+        .map(|backup| storage.read_backup::<Counter>(backup).unwrap().count)
+        .collect();
+
+    assert_eq!(
+        backups.iter().map(|b| b.index()).collect::<Vec<_>>(),
+        vec![1, 2]
+    );
+    assert_eq!(counts, vec![3, 2]);
+}
+
+#[test]
+fn no_backups_by_default() {
+    let (dir, storage) = common::storage();
+
+    write_counts(&storage, &[1, 2]);
+
+    assert!(storage.backups::<Counter>("counter").unwrap().is_empty());
+    assert_eq!(
+        common::entries(&dir.path().join("data")),
+        vec!["counter.ron"]
+    );
+}
+
+#[test]
+fn lowering_the_count_removes_older_backups() {
+    let (_dir, storage) = common::storage();
+    write_counts(&storage.clone().with_backups(3), &[1, 2, 3, 4]);
+
+    let storage = storage.with_backups(1);
+    write_counts(&storage, &[5]);
+
+    let backups = storage.backups::<Counter>("counter").unwrap();
+    assert_eq!(
+        backups.iter().map(|b| b.index()).collect::<Vec<_>>(),
+        vec![1]
+    );
+    assert_eq!(
+        storage.read_backup::<Counter>(&backups[0]).unwrap().count,
+        4
+    );
+}
+
+#[test]
+fn restores_a_backup() {
+    let (_dir, storage) = common::storage();
+    let storage = storage.with_backups(3);
+    let mut handle = write_counts(&storage, &[1, 2, 3]);
+
+    let oldest = storage
+        .backups::<Counter>("counter")
+        .unwrap()
+        .pop()
+        .unwrap();
+    storage.restore_backup(&mut handle, &oldest).unwrap();
+    assert_eq!(handle.get_store().count, 1);
+
+    let mut reread = StoreHandle::<Counter>::new("counter");
+    storage.read(&mut reread).unwrap();
+    assert_eq!(reread.get_store().count, 1);
+
+    // The replaced version is kept, so the restore can be undone.
+    let latest = &storage.backups::<Counter>("counter").unwrap()[0];
+    assert_eq!(storage.read_backup::<Counter>(latest).unwrap().count, 3);
+}
+
+#[test]
+fn restore_last_good_skips_broken_backups() {
+    let (dir, storage) = common::storage();
+    let storage = storage.with_backups(3);
+    write_counts(&storage, &[1, 2, 3]);
+
+    fs::write(dir.path().join("data/counter.ron.1.bak"), "(count: ").unwrap();
+
+    let mut manager = StoreManager::<Counter>::new(&storage, "counter").unwrap();
+    let backup = manager.restore_last_good().unwrap();
+
+    assert_eq!(backup.index(), 2);
+    assert_eq!(manager.get_store().count, 1);
+    assert_eq!(manager.get_store_alive().unwrap().count, 1);
+}
+
+#[test]
+fn restore_last_good_fails_without_backups() {
+    let (_dir, storage) = common::storage();
+    let mut handle = StoreHandle::<Counter>::new("counter");
+
+    assert!(matches!(
+        storage.restore_last_good(&mut handle),
+        Err(StoreError::NoBackup(id)) if id == "counter"
+    ));
+}
@@ -0,0 +1,127 @@
+#![cfg(any(feature = "bincode", feature = "msgpack", feature = "cbor"))]
+
+mod common;
+
+use std::collections::BTreeMap;
+use std::fs;
+
+use rusty_store::{Format, RecoveryPolicy, StoreError, StoreManager, Storing, StoringType};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Clone)]
+pub struct Entry {
+    pub key: String,
+    pub value: Option<f64>,
+    pub bytes: Vec<u8>,
+}
+
+fn entries() -> BTreeMap<u32, Entry> {
+    (0..100)
+        .map(|id| {
+            let entry = Entry {
+                key: format!("entry-{}", id),
+                value: (id % 2 == 0).then_some(id as f64 / 3.0),
+                bytes: vec![id as u8; 16],
+            };
+            (id, entry)
+        })
+        .collect()
+}
+
+macro_rules! binary_format {
+    ($module:ident, $feature:literal, $format:expr, $is_parse_error:pat) => {
+        #[cfg(feature = $feature)]
+        mod $module {
+            use super::*;
+
+            #[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+            pub struct Cache {
+                pub entries: BTreeMap<u32, Entry>,
+            }
+
+            impl Storing for Cache {
+                fn store_type() -> StoringType {
+                    StoringType::Cache
+                }
+
+                fn format() -> Format {
+                    $format
+                }
+
+                fn recovery_policy() -> Option<RecoveryPolicy> {
+                    Some(RecoveryPolicy::MoveAside)
+                }
+            }
+
+            fn cache_path(dir: &tempfile::TempDir) -> std::path::PathBuf {
+                let name = format!("cache.{}", Cache::format().extension());
+                dir.path().join("cache").join(name)
+            }
+
+            #[test]
+            fn round_trips() {
+                let (dir, storage) = common::storage();
+                let mut manager = StoreManager::<Cache>::new(&storage, "cache").unwrap();
+
+                manager
+                    .modify_store(|store| store.entries = entries())
+                    .unwrap();
+
+                let contents = fs::read(cache_path(&dir)).unwrap();
+                assert!(contents.iter().any(|byte| !byte.is_ascii()));
+
+                let reloaded = StoreManager::<Cache>::new(&storage, "cache").unwrap();
+                assert_eq!(reloaded.get_store().entries, entries());
+            }
+
+            #[test]
+            fn corrupt_files_are_recovered() {
+                let (dir, storage) = common::storage();
+                let mut manager = StoreManager::<Cache>::new(&storage, "cache").unwrap();
+                manager
+                    .modify_store(|store| store.entries = entries())
+                    .unwrap();
+
+                let path = cache_path(&dir);
+                let mut contents = fs::read(&path).unwrap();
+                contents.truncate(contents.len() / 2);
+                fs::write(&path, &contents).unwrap();
+
+                let reloaded = StoreManager::<Cache>::new(&storage, "cache").unwrap();
+                assert!(reloaded.get_store().entries.is_empty());
+                assert_eq!(common::entries(&dir.path().join("cache")).len(), 2);
+            }
+
+            #[test]
+            fn parse_errors_are_reported() {
+                let (dir, storage) = common::storage();
+                let mut manager = StoreManager::<Cache>::new(&storage, "cache").unwrap();
+                manager
+                    .modify_store(|store| store.entries = entries())
+                    .unwrap();
+
+                let path = cache_path(&dir);
+                let contents = fs::read(&path).unwrap();
+                fs::write(&path, &contents[..contents.len() / 2]).unwrap();
+
+                let result = manager.reload_if_changed();
+                assert!(matches!(result, Err($is_parse_error)), "{:?}", result);
+                assert_eq!(manager.get_store().entries, entries());
+            }
+        }
+    };
+}
+
+binary_format!(
+    bincode,
+    "bincode",
+    Format::Bincode,
+    StoreError::BincodeParse(_)
+);
+binary_format!(
+    msgpack,
+    "msgpack",
+    Format::MessagePack,
+    StoreError::MessagePackParse(_)
+);
+binary_format!(cbor, "cbor", Format::Cbor, StoreError::CborParse(_));
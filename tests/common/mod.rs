@@ -0,0 +1,29 @@
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::PathBuf;
+
+use rusty_store::Storage;
+use tempfile::TempDir;
+
+/// Returns a `Storage` whose cache, data and config directories live in a fresh temporary directory.
+pub fn storage() -> (TempDir, Storage) {
+    let dir = tempfile::tempdir().expect("Failed to create temporary directory");
+    let storage = Storage::from_dirs(
+        dir.path().join("cache"),
+        dir.path().join("data"),
+        dir.path().join("config"),
+    );
+    (dir, storage)
+}
+
+/// Returns the names of every entry in `dir` except lock files, sorted.
+pub fn entries(dir: &PathBuf) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .expect("Failed to read directory")
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|name| !name.ends_with(".lock"))
+        .collect();
+    names.sort();
+    names
+}
@@ -0,0 +1,228 @@
+mod common;
+
+use std::fs;
+
+use rusty_store::{RecoveryPolicy, StoreError, StoreManager, Storing};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Storing)]
+pub struct Log {
+    pub lines: Vec<String>,
+}
+
+#[cfg(any(feature = "zstd", feature = "gzip"))]
+fn lines() -> Vec<String> {
+    (0..1000)
+        .map(|id| format!("request {} served from the cache", id % 10))
+        .collect()
+}
+
+#[test]
+#[cfg(not(feature = "gzip"))]
+fn unsupported_compression_is_not_corrupt() {
+    let (dir, storage) = common::storage();
+    let storage = storage.with_recovery_policy(RecoveryPolicy::MoveAside);
+    fs::create_dir_all(dir.path().join("data")).unwrap();
+    fs::write(dir.path().join("data/log.ron"), b"\x1F\x8B\x08\x00").unwrap();
+
+    let result = StoreManager::<Log>::new(&storage, "log");
+
+    assert!(
+        matches!(&result, Err(StoreError::Read(err)) if err.kind() == std::io::ErrorKind::Unsupported)
+    );
+    assert_eq!(common::entries(&dir.path().join("data")), vec!["log.ron"]);
+}
+
+#[cfg(feature = "bincode")]
+mod bincode {
+    use super::*;
+    use rusty_store::Format;
+
+    /// Its first field can be written as the magic bytes of zstd or gzip.
+    #[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+    pub struct Binary {
+        pub a: u32,
+        pub b: u32,
+    }
+
+    impl Storing for Binary {
+        fn format() -> Format {
+            Format::Bincode
+        }
+    }
+
+    #[test]
+    fn stores_starting_like_compressed_files_round_trip() {
+        let (dir, storage) = common::storage();
+        let mut manager = StoreManager::<Binary>::new(&storage, "binary").unwrap();
+
+        for (a, magic) in [
+            (0xFD2F_B528, &[0x28, 0xB5, 0x2F, 0xFD][..]),
+            (0x0007_8B1F, &[0x1F, 0x8B]),
+        ] {
+            manager
+                .modify_store(|store| {
+                    store.a = a;
+                    store.b = 7;
+                })
+                .unwrap();
+
+            let contents = fs::read(dir.path().join("data/binary.bin")).unwrap();
+            assert!(contents.starts_with(magic), "{:?}", contents);
+            let reloaded = StoreManager::<Binary>::new(&storage, "binary").unwrap();
+            assert_eq!(*reloaded.get_store(), Binary { a, b: 7 });
+        }
+    }
+}
+
+macro_rules! compression {
+    ($module:ident, $feature:literal, $compression:expr, $magic:expr, $decode:expr) => {
+        #[cfg(feature = $feature)]
+        mod $module {
+            use super::*;
+            use rusty_store::Compression;
+
+            #[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+            pub struct CompressedLog {
+                pub lines: Vec<String>,
+            }
+
+            impl Storing for CompressedLog {
+                fn compression() -> Option<Compression> {
+                    Some($compression)
+                }
+
+                fn recovery_policy() -> Option<RecoveryPolicy> {
+                    Some(RecoveryPolicy::MoveAside)
+                }
+            }
+
+            #[test]
+            fn store_compression_round_trips() {
+                let (dir, storage) = common::storage();
+                let mut manager =
+                    StoreManager::<CompressedLog>::new(&storage, "compressed_log").unwrap();
+
+                manager.modify_store(|store| store.lines = lines()).unwrap();
+
+                let contents = fs::read(dir.path().join("data/compressed_log.ron")).unwrap();
+                assert!(contents.starts_with($magic));
+                let reloaded =
+                    StoreManager::<CompressedLog>::new(&storage, "compressed_log").unwrap();
+                assert_eq!(reloaded.get_store().lines, lines());
+            }
+
+            #[test]
+            fn compressed_files_are_plain_streams() {
+                let (dir, storage) = common::storage();
+                let mut manager =
+                    StoreManager::<CompressedLog>::new(&storage, "compressed_log").unwrap();
+
+                manager.modify_store(|store| store.lines = lines()).unwrap();
+
+                let contents = fs::read(dir.path().join("data/compressed_log.ron")).unwrap();
+                let decode: fn(&[u8]) -> Vec<u8> = $decode;
+                let store: CompressedLog = ron::de::from_bytes(&decode(&contents)).unwrap();
+                assert_eq!(store.lines, lines());
+            }
+
+            #[test]
+            fn storage_compression_applies_to_every_store() {
+                let (dir, storage) = common::storage();
+                let storage = storage.with_compression($compression);
+                let mut manager = StoreManager::<Log>::new(&storage, "log").unwrap();
+
+                manager.modify_store(|store| store.lines = lines()).unwrap();
+
+                let contents = fs::read(dir.path().join("data/log.ron")).unwrap();
+                assert!(contents.starts_with($magic));
+            }
+
+            #[test]
+            fn shrinks_repetitive_stores() {
+                let (dir, storage) = common::storage();
+                let mut plain = StoreManager::<Log>::new(&storage, "log").unwrap();
+                let mut compressed =
+                    StoreManager::<CompressedLog>::new(&storage, "compressed_log").unwrap();
+
+                plain.modify_store(|store| store.lines = lines()).unwrap();
+                compressed
+                    .modify_store(|store| store.lines = lines())
+                    .unwrap();
+
+                let size = |name: &str| fs::metadata(dir.path().join("data").join(name)).unwrap();
+                let plain = size("log.ron").len();
+                let compressed = size("compressed_log.ron").len();
+                assert!(compressed * 10 < plain, "{} vs {}", compressed, plain);
+            }
+
+            #[test]
+            fn uncompressed_files_are_read_and_compressed_on_save() {
+                let (dir, storage) = common::storage();
+                fs::create_dir_all(dir.path().join("data")).unwrap();
+                let path = dir.path().join("data/compressed_log.ron");
+                fs::write(&path, "(lines: [\"kept\"])").unwrap();
+
+                let mut manager =
+                    StoreManager::<CompressedLog>::new(&storage, "compressed_log").unwrap();
+                assert_eq!(manager.get_store().lines, vec!["kept"]);
+
+                manager
+                    .modify_store(|store| store.lines.push("added".to_owned()))
+                    .unwrap();
+
+                assert!(fs::read(&path).unwrap().starts_with($magic));
+                let reloaded =
+                    StoreManager::<CompressedLog>::new(&storage, "compressed_log").unwrap();
+                assert_eq!(reloaded.get_store().lines, vec!["kept", "added"]);
+            }
+
+            #[test]
+            fn corrupt_compressed_files_fail_to_parse() {
+                let (dir, storage) = common::storage();
+                fs::create_dir_all(dir.path().join("data")).unwrap();
+                let mut contents = $magic.to_vec();
+                contents.extend_from_slice(b"not compressed");
+                fs::write(dir.path().join("data/log.ron"), &contents).unwrap();
+
+                let result = StoreManager::<Log>::new(&storage, "log");
+
+                assert!(matches!(result, Err(StoreError::Decompression(_))));
+            }
+
+            #[test]
+            fn corrupt_compressed_files_are_recovered() {
+                let (dir, storage) = common::storage();
+                fs::create_dir_all(dir.path().join("data")).unwrap();
+                let mut contents = $magic.to_vec();
+                contents.extend_from_slice(b"not compressed");
+                fs::write(dir.path().join("data/compressed_log.ron"), &contents).unwrap();
+
+                let manager =
+                    StoreManager::<CompressedLog>::new(&storage, "compressed_log").unwrap();
+
+                assert!(manager.get_store().lines.is_empty());
+                let entries = common::entries(&dir.path().join("data"));
+                assert_eq!(entries.len(), 2);
+                assert!(entries[1].starts_with("compressed_log.ron.corrupt-"));
+            }
+        }
+    };
+}
+
+compression!(
+    zstd,
+    "zstd",
+    Compression::Zstd,
+    &[0x28, 0xB5, 0x2F, 0xFD],
+    |contents| ::zstd::stream::decode_all(contents).unwrap()
+);
+compression!(gzip, "gzip", Compression::Gzip, &[0x1F, 0x8B], |contents| {
+    use std::io::Read;
+
+    let mut decompressed = Vec::new();
+    ::flate2::read::GzDecoder::new(contents)
+        .read_to_end(&mut decompressed)
+        .unwrap();
+    decompressed
+});
@@ -0,0 +1,107 @@
+mod common;
+
+use std::fs;
+
+use rusty_store::{StoreError, StoreHandle, StoreManager, Storing};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Storing)]
+pub struct Counter {
+    pub count: u32,
+}
+
+#[test]
+fn save_detects_external_modification() {
+    let (dir, storage) = common::storage();
+    let mut manager = StoreManager::<Counter>::new(&storage, "counter").unwrap();
+
+    fs::write(dir.path().join("data/counter.ron"), "(count: 10)").unwrap();
+
+    let result = manager.modify_store(|store| store.count += 1);
+    assert!(matches!(result, Err(StoreError::Conflict(id)) if id == "counter"));
+    assert_eq!(
+        fs::read_to_string(dir.path().join("data/counter.ron")).unwrap(),
+        "(count: 10)"
+    );
+}
+
+#[test]
+fn save_after_reload_succeeds() {
+    let (dir, storage) = common::storage();
+    let mut manager = StoreManager::<Counter>::new(&storage, "counter").unwrap();
+
+    fs::write(dir.path().join("data/counter.ron"), "(count: 10)").unwrap();
+
+    assert_eq!(manager.get_store_alive().unwrap().count, 10);
+    manager.modify_store(|store| store.count += 1).unwrap();
+    manager.modify_store(|store| store.count += 1).unwrap();
+    assert_eq!(manager.get_store_alive().unwrap().count, 12);
+}
+
+#[test]
+fn two_managers_do_not_overwrite_each_other() {
+    let (_dir, storage) = common::storage();
+    let mut first = StoreManager::<Counter>::new(&storage, "counter").unwrap();
+    let mut second = StoreManager::<Counter>::new(&storage, "counter").unwrap();
+
+    first.modify_store(|store| store.count = 1).unwrap();
+
+    assert!(matches!(
+        second.modify_store(|store| store.count = 2),
+        Err(StoreError::Conflict(_))
+    ));
+}
+
+#[test]
+fn save_force_overwrites_external_modification() {
+    let (dir, storage) = common::storage();
+    let mut manager = StoreManager::<Counter>::new(&storage, "counter").unwrap();
+
+    fs::write(dir.path().join("data/counter.ron"), "(count: 10)").unwrap();
+
+    manager.get_store_mut().count = 1;
+    manager.save_force().unwrap();
+    assert_eq!(manager.get_store_alive().unwrap().count, 1);
+}
+
+#[test]
+fn save_or_resolve_receives_both_versions() {
+    let (dir, storage) = common::storage();
+    let mut manager = StoreManager::<Counter>::new(&storage, "counter").unwrap();
+
+    fs::write(dir.path().join("data/counter.ron"), "(count: 10)").unwrap();
+
+    manager.get_store_mut().count = 3;
+    manager
+        .save_or_resolve(|ours, theirs| Counter {
+            count: ours.count + theirs.count,
+        })
+        .unwrap();
+
+    assert_eq!(manager.get_store().count, 13);
+    assert_eq!(manager.get_store_alive().unwrap().count, 13);
+}
+
+#[test]
+fn save_or_resolve_skips_resolution_without_conflict() {
+    let (_dir, storage) = common::storage();
+    let mut manager = StoreManager::<Counter>::new(&storage, "counter").unwrap();
+
+    manager.get_store_mut().count = 3;
+    manager
+        .save_or_resolve(|_, _| panic!("No conflict to resolve"))
+        .unwrap();
+
+    assert_eq!(manager.get_store_alive().unwrap().count, 3);
+}
+
+#[test]
+fn unread_handles_write_unconditionally() {
+    let (_dir, storage) = common::storage();
+    let mut handle = StoreHandle::<Counter>::new("counter");
+    storage.write(&mut handle).unwrap();
+
+    let mut fresh = StoreHandle::<Counter>::new("counter");
+    fresh.get_store_mut().count = 5;
+    storage.write_if_unchanged(&mut fresh).unwrap();
+}
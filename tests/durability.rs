@@ -0,0 +1,29 @@
+mod common;
+
+use rusty_store::{Durability, StoreHandle, Storing};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default, Storing)]
+pub struct Plain {
+    pub value: u32,
+}
+
+#[test]
+fn every_durability_level_round_trips() {
+    for durability in [
+        Durability::None,
+        Durability::File,
+        Durability::FileAndDirectory,
+    ] {
+        let (_dir, storage) = common::storage();
+        let storage = storage.with_durability(durability);
+
+        let mut handle = StoreHandle::<Plain>::new("plain");
+        handle.get_store_mut().value = 7;
+        storage.write(&mut handle).unwrap();
+
+        let mut reread = StoreHandle::<Plain>::new("plain");
+        storage.read(&mut reread).unwrap();
+        assert_eq!(reread.get_store().value, 7);
+    }
+}
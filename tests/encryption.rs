@@ -0,0 +1,155 @@
+#![cfg(feature = "encryption")]
+
+mod common;
+
+use std::fs;
+
+use rusty_store::{KeyFile, Passphrase, StoreError, StoreManager, Storing, KEY_LEN};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+pub struct Tokens {
+    pub api_token: String,
+}
+
+impl Storing for Tokens {
+    fn encrypted() -> bool {
+        true
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Storing)]
+pub struct Counter {
+    pub count: u32,
+}
+
+const TOKEN: &str = "secret-api-token";
+
+fn save_token(storage: &rusty_store::Storage) {
+    let mut manager = StoreManager::<Tokens>::new(storage, "tokens").unwrap();
+    manager
+        .modify_store(|store| store.api_token = TOKEN.to_owned())
+        .unwrap();
+}
+
+#[test]
+fn key_file_round_trips() {
+    let (dir, storage) = common::storage();
+    let key_path = dir.path().join("keys/store.key");
+    let storage = storage.with_key_provider(KeyFile::new(&key_path));
+
+    save_token(&storage);
+
+    assert_eq!(fs::read(&key_path).unwrap().len(), KEY_LEN);
+    let contents = fs::read(dir.path().join("data/tokens.ron")).unwrap();
+    assert!(!String::from_utf8_lossy(&contents).contains(TOKEN));
+
+    let reloaded = StoreManager::<Tokens>::new(&storage, "tokens").unwrap();
+    assert_eq!(reloaded.get_store().api_token, TOKEN);
+}
+
+#[test]
+fn passphrase_round_trips() {
+    let (dir, storage) = common::storage();
+
+    save_token(
+        &storage
+            .clone()
+            .with_key_provider(Passphrase::new("hunter2")),
+    );
+
+    let contents = fs::read(dir.path().join("data/tokens.ron")).unwrap();
+    assert!(!String::from_utf8_lossy(&contents).contains(TOKEN));
+    let storage = storage.with_key_provider(Passphrase::new("hunter2"));
+    let reloaded = StoreManager::<Tokens>::new(&storage, "tokens").unwrap();
+    assert_eq!(reloaded.get_store().api_token, TOKEN);
+}
+
+#[test]
+fn wrong_key_is_reported() {
+    let (_dir, storage) = common::storage();
+    save_token(
+        &storage
+            .clone()
+            .with_key_provider(Passphrase::new("hunter2")),
+    );
+
+    let storage = storage.with_key_provider(Passphrase::new("hunter3"));
+    let result = StoreManager::<Tokens>::new(&storage, "tokens");
+
+    assert!(matches!(result, Err(StoreError::WrongKey)));
+}
+
+#[test]
+fn tampering_is_reported() {
+    let (dir, storage) = common::storage();
+    let storage = storage.with_key_provider(KeyFile::new(dir.path().join("store.key")));
+    save_token(&storage);
+
+    let path = dir.path().join("data/tokens.ron");
+    let mut contents = fs::read(&path).unwrap();
+    *contents.last_mut().unwrap() ^= 1;
+    fs::write(&path, &contents).unwrap();
+    let result = StoreManager::<Tokens>::new(&storage, "tokens");
+
+    assert!(matches!(result, Err(StoreError::Tampered)));
+}
+
+#[test]
+fn missing_key_provider_is_reported() {
+    let (_dir, storage) = common::storage();
+
+    let result = StoreManager::<Tokens>::new(&storage, "tokens");
+
+    assert!(matches!(result, Err(StoreError::NoKeyProvider)));
+}
+
+#[test]
+fn plain_files_are_rejected() {
+    let (dir, storage) = common::storage();
+    let storage = storage.with_key_provider(KeyFile::new(dir.path().join("store.key")));
+    save_token(&storage);
+    let path = dir.path().join("data/tokens.ron");
+    fs::write(&path, "(api_token: \"forged-token\")").unwrap();
+
+    let result = StoreManager::<Tokens>::new(&storage, "tokens");
+
+    assert!(matches!(result, Err(StoreError::Unencrypted)));
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "(api_token: \"forged-token\")"
+    );
+}
+
+#[test]
+fn plain_files_are_read_and_encrypted_on_save_when_migrating() {
+    let (dir, storage) = common::storage();
+    let storage = storage
+        .with_key_provider(KeyFile::new(dir.path().join("store.key")))
+        .with_plaintext_migration(true);
+    let path = dir.path().join("data/tokens.ron");
+    fs::create_dir_all(dir.path().join("data")).unwrap();
+    fs::write(&path, "(api_token: \"old-token\")").unwrap();
+
+    let mut manager = StoreManager::<Tokens>::new(&storage, "tokens").unwrap();
+    assert_eq!(manager.get_store().api_token, "old-token");
+    manager
+        .modify_store(|store| store.api_token = TOKEN.to_owned())
+        .unwrap();
+
+    let contents = fs::read(&path).unwrap();
+    assert!(!String::from_utf8_lossy(&contents).contains(TOKEN));
+}
+
+#[test]
+fn other_stores_stay_plain() {
+    let (dir, storage) = common::storage();
+    let storage = storage.with_key_provider(KeyFile::new(dir.path().join("store.key")));
+    let mut manager = StoreManager::<Counter>::new(&storage, "counter").unwrap();
+
+    manager.modify_store(|store| store.count = 3).unwrap();
+
+    let contents = fs::read_to_string(dir.path().join("data/counter.ron")).unwrap();
+    assert!(contents.contains("count: 3"), "{}", contents);
+    assert!(!dir.path().join("store.key").exists());
+}
@@ -0,0 +1,199 @@
+mod common;
+
+use std::fs;
+
+use rusty_store::{Format, StoreManager, Storing};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Storing)]
+pub struct AppData {
+    pub count: u32,
+}
+
+#[test]
+fn ron_is_the_default() {
+    let (dir, storage) = common::storage();
+    let mut manager = StoreManager::<AppData>::new(&storage, "app").unwrap();
+
+    manager.modify_store(|store| store.count = 3).unwrap();
+
+    assert_eq!(AppData::format(), Format::Ron);
+    let contents = fs::read_to_string(dir.path().join("data/app.ron")).unwrap();
+    assert!(contents.contains("count: 3"), "{}", contents);
+}
+
+#[cfg(any(feature = "json", feature = "toml", feature = "yaml"))]
+mod text {
+    use super::*;
+    use rusty_store::{StoreError, StoringType};
+
+    #[derive(Serialize, Deserialize, Default, Debug, PartialEq, Clone)]
+    pub struct Server {
+        pub host: String,
+        pub port: u16,
+        pub tags: Vec<String>,
+    }
+
+    fn server() -> Server {
+        Server {
+            host: "localhost".to_owned(),
+            port: 8080,
+            tags: vec!["a".to_owned(), "b".to_owned()],
+        }
+    }
+
+    macro_rules! format_store {
+        ($name:ident, $format:expr) => {
+            #[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+            pub struct $name {
+                pub name: String,
+                pub server: Server,
+            }
+
+            impl Storing for $name {
+                fn store_type() -> StoringType {
+                    StoringType::Config
+                }
+
+                fn format() -> Format {
+                    $format
+                }
+            }
+
+            impl Sample for $name {
+                fn sample() -> Self {
+                    Self {
+                        name: "app".to_owned(),
+                        server: server(),
+                    }
+                }
+            }
+        };
+    }
+
+    trait Sample {
+        fn sample() -> Self;
+    }
+
+    /// Writes a sample store, checks the file with `check` and reads it back.
+    fn round_trip<T: Storing + Sample>(check: impl Fn(&str)) -> T {
+        let (dir, storage) = common::storage();
+        let mut manager = StoreManager::<T>::new(&storage, "settings").unwrap();
+        *manager.get_store_mut() = T::sample();
+        manager.save().unwrap();
+
+        let path = format!("config/settings.{}", T::format().extension());
+        check(&fs::read_to_string(dir.path().join(path)).unwrap());
+
+        let mut reloaded = StoreManager::<T>::new(&storage, "settings").unwrap();
+        reloaded.get_store_alive().unwrap();
+        std::mem::take(reloaded.get_store_mut())
+    }
+
+    #[cfg(feature = "json")]
+    mod json {
+        use super::*;
+
+        format_store!(JsonSettings, Format::Json);
+
+        #[test]
+        fn round_trips() {
+            let store: JsonSettings = round_trip(|contents| {
+                assert!(contents.contains("\"port\": 8080"), "{}", contents);
+            });
+            assert_eq!(store, JsonSettings::sample());
+        }
+
+        #[test]
+        fn parse_errors_are_reported() {
+            let (dir, storage) = common::storage();
+            fs::create_dir_all(dir.path().join("config")).unwrap();
+            fs::write(dir.path().join("config/settings.json"), "{\"name\": ").unwrap();
+
+            let result = StoreManager::<JsonSettings>::new(&storage, "settings");
+
+            assert!(matches!(result, Err(StoreError::JsonParse(_))));
+        }
+    }
+
+    #[cfg(feature = "toml")]
+    mod toml {
+        use super::*;
+
+        format_store!(TomlSettings, Format::Toml);
+
+        #[test]
+        fn round_trips() {
+            let store: TomlSettings = round_trip(|contents| {
+                assert!(contents.contains("[server]"), "{}", contents);
+                assert!(contents.contains("port = 8080"), "{}", contents);
+            });
+            assert_eq!(store, TomlSettings::sample());
+        }
+
+        #[test]
+        fn lives_next_to_ron_stores() {
+            let (dir, storage) = common::storage();
+            let mut settings = StoreManager::<TomlSettings>::new(&storage, "settings").unwrap();
+            let mut data = StoreManager::<AppData>::new(&storage, "app").unwrap();
+
+            settings
+                .modify_store(|store| store.name = "ops".to_owned())
+                .unwrap();
+            data.modify_store(|store| store.count = 1).unwrap();
+
+            let settings = fs::read_to_string(dir.path().join("config/settings.toml")).unwrap();
+            let data = fs::read_to_string(dir.path().join("data/app.ron")).unwrap();
+            assert!(settings.contains("name = \"ops\""), "{}", settings);
+            assert!(data.contains("count: 1"), "{}", data);
+        }
+
+        #[test]
+        fn parse_errors_are_reported() {
+            let (dir, storage) = common::storage();
+            fs::create_dir_all(dir.path().join("config")).unwrap();
+            fs::write(dir.path().join("config/settings.toml"), "name = ").unwrap();
+
+            let result = StoreManager::<TomlSettings>::new(&storage, "settings");
+
+            assert!(matches!(result, Err(StoreError::TomlParse(_))));
+        }
+
+        #[test]
+        fn invalid_utf8_is_a_parse_error() {
+            let (dir, storage) = common::storage();
+            fs::create_dir_all(dir.path().join("config")).unwrap();
+            fs::write(dir.path().join("config/settings.toml"), b"name = \"\xff\"").unwrap();
+
+            let result = StoreManager::<TomlSettings>::new(&storage, "settings");
+
+            assert!(matches!(result, Err(StoreError::TomlParse(_))));
+        }
+    }
+
+    #[cfg(feature = "yaml")]
+    mod yaml {
+        use super::*;
+
+        format_store!(YamlSettings, Format::Yaml);
+
+        #[test]
+        fn round_trips() {
+            let store: YamlSettings = round_trip(|contents| {
+                assert!(contents.contains("port: 8080"), "{}", contents);
+            });
+            assert_eq!(store, YamlSettings::sample());
+        }
+
+        #[test]
+        fn parse_errors_are_reported() {
+            let (dir, storage) = common::storage();
+            fs::create_dir_all(dir.path().join("config")).unwrap();
+            fs::write(dir.path().join("config/settings.yaml"), "name: [").unwrap();
+
+            let result = StoreManager::<YamlSettings>::new(&storage, "settings");
+
+            assert!(matches!(result, Err(StoreError::YamlParse(_))));
+        }
+    }
+}
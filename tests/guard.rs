@@ -0,0 +1,105 @@
+mod common;
+
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+
+use rusty_store::{StoreError, StoreManager, Storing};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default, Debug, Storing)]
+pub struct Counter {
+    pub count: u32,
+}
+
+#[test]
+fn saves_when_dropped() {
+    let (_dir, storage) = common::storage();
+    let mut manager = StoreManager::<Counter>::new(&storage, "counter").unwrap();
+
+    {
+        let mut store = manager.edit();
+        store.count += 2;
+    }
+
+    assert_eq!(manager.get_store_alive().unwrap().count, 2);
+}
+
+#[test]
+fn does_not_save_when_only_read() {
+    let (dir, storage) = common::storage();
+    let mut manager = StoreManager::<Counter>::new(&storage, "counter").unwrap();
+    fs::write(dir.path().join("data/counter.ron"), "(count: 10)").unwrap();
+
+    let store = manager.edit();
+    assert_eq!(store.count, 0);
+    assert!(!store.is_dirty());
+    store.commit().unwrap();
+
+    assert_eq!(
+        fs::read_to_string(dir.path().join("data/counter.ron")).unwrap(),
+        "(count: 10)"
+    );
+}
+
+#[test]
+fn commit_returns_the_error() {
+    let (dir, storage) = common::storage();
+    let mut manager = StoreManager::<Counter>::new(&storage, "counter").unwrap();
+    fs::write(dir.path().join("data/counter.ron"), "(count: 10)").unwrap();
+
+    let mut store = manager.edit();
+    store.count += 1;
+
+    assert!(matches!(store.commit(), Err(StoreError::Conflict(_))));
+}
+
+#[test]
+fn failed_save_on_drop_is_not_fatal() {
+    let (dir, storage) = common::storage();
+    let mut manager = StoreManager::<Counter>::new(&storage, "counter").unwrap();
+    fs::write(dir.path().join("data/counter.ron"), "(count: 10)").unwrap();
+
+    {
+        let mut store = manager.edit();
+        store.count += 1;
+    }
+
+    // The modification is rolled back like a failed `modify_store`.
+    assert_eq!(manager.get_store().count, 0);
+    assert_eq!(manager.get_store_alive().unwrap().count, 10);
+}
+
+#[test]
+fn failed_commit_rolls_back() {
+    let (dir, storage) = common::storage();
+    let mut manager = StoreManager::<Counter>::new(&storage, "counter").unwrap();
+    fs::write(dir.path().join("data/counter.ron"), "(count: 10)").unwrap();
+
+    let mut store = manager.edit();
+    store.count += 1;
+    store.count += 1;
+    assert!(store.commit().is_err());
+
+    assert_eq!(manager.get_store().count, 0);
+}
+
+#[test]
+fn panics_discard_the_modification() {
+    let (dir, storage) = common::storage();
+    let mut manager = StoreManager::<Counter>::new(&storage, "counter").unwrap();
+    manager.modify_store(|store| store.count = 3).unwrap();
+    let before = fs::read_to_string(dir.path().join("data/counter.ron")).unwrap();
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let mut store = manager.edit();
+        store.count = 40;
+        panic!("interrupted halfway through the edit");
+    }));
+
+    assert!(result.is_err());
+    assert_eq!(manager.get_store().count, 3);
+    assert_eq!(
+        fs::read_to_string(dir.path().join("data/counter.ron")).unwrap(),
+        before
+    );
+}
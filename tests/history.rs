@@ -0,0 +1,130 @@
+mod common;
+
+use std::fs;
+
+use rusty_store::{StoreManager, Storing};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Storing)]
+pub struct Counter {
+    pub count: u32,
+}
+
+fn counter(storage: &rusty_store::Storage, depth: usize) -> StoreManager<Counter> {
+    let mut manager = StoreManager::<Counter>::new(storage, "counter").unwrap();
+    manager.enable_history(depth);
+    manager
+}
+
+#[test]
+fn undo_and_redo_are_saved() {
+    let (_dir, storage) = common::storage();
+    let mut manager = counter(&storage, 10);
+
+    for count in 1..=3 {
+        manager.modify_store(|store| store.count = count).unwrap();
+    }
+
+    assert!(manager.undo().unwrap());
+    assert!(manager.undo().unwrap());
+    assert_eq!(manager.get_store().count, 1);
+    assert_eq!(manager.get_store_alive().unwrap().count, 1);
+
+    assert!(manager.redo().unwrap());
+    assert_eq!(manager.get_store().count, 2);
+    assert_eq!(manager.get_store_alive().unwrap().count, 2);
+
+    assert!(manager.undo().unwrap());
+    assert!(manager.undo().unwrap());
+    assert!(!manager.undo().unwrap());
+    assert_eq!(manager.get_store().count, 0);
+}
+
+#[test]
+fn history_is_bounded_by_depth() {
+    let (_dir, storage) = common::storage();
+    let mut manager = counter(&storage, 2);
+
+    for count in 1..=5 {
+        manager.modify_store(|store| store.count = count).unwrap();
+    }
+
+    assert!(manager.undo().unwrap());
+    assert!(manager.undo().unwrap());
+    assert!(!manager.can_undo());
+    assert!(!manager.undo().unwrap());
+    assert_eq!(manager.get_store().count, 3);
+}
+
+#[test]
+fn new_modification_clears_redo() {
+    let (_dir, storage) = common::storage();
+    let mut manager = counter(&storage, 10);
+
+    manager.modify_store(|store| store.count = 1).unwrap();
+    manager.modify_store(|store| store.count = 2).unwrap();
+    manager.undo().unwrap();
+    assert!(manager.can_redo());
+
+    manager.modify_store(|store| store.count = 5).unwrap();
+
+    assert!(!manager.can_redo());
+    assert!(!manager.redo().unwrap());
+    manager.undo().unwrap();
+    assert_eq!(manager.get_store().count, 1);
+}
+
+#[test]
+fn reloads_and_unchanged_commits_are_not_recorded() {
+    let (_dir, storage) = common::storage();
+    let mut manager = counter(&storage, 10);
+
+    manager.modify_store(|store| store.count = 1).unwrap();
+    manager.modify_store(|store| store.count = 1).unwrap();
+    let mut other = StoreManager::<Counter>::new(&storage, "counter").unwrap();
+    other.modify_store(|store| store.count = 7).unwrap();
+    manager.get_store_alive().unwrap();
+
+    assert!(manager.undo().unwrap());
+    assert_eq!(manager.get_store().count, 0);
+    assert!(!manager.can_undo());
+}
+
+#[test]
+fn failed_undo_keeps_store_and_history() {
+    let (dir, storage) = common::storage();
+    let mut manager = counter(&storage, 10);
+    manager.modify_store(|store| store.count = 1).unwrap();
+
+    // Replacing the store file with a directory makes every write fail.
+    let path = dir.path().join("data/counter.ron");
+    fs::remove_file(&path).unwrap();
+    fs::create_dir(&path).unwrap();
+
+    assert!(manager.undo().is_err());
+    assert_eq!(manager.get_store().count, 1);
+    assert!(manager.can_undo());
+    assert!(!manager.can_redo());
+
+    fs::remove_dir(&path).unwrap();
+    manager.save_force().unwrap();
+    assert!(manager.undo().unwrap());
+    assert_eq!(manager.get_store_alive().unwrap().count, 0);
+}
+
+#[test]
+fn disabled_history_records_nothing() {
+    let (_dir, storage) = common::storage();
+    let mut manager = StoreManager::<Counter>::new(&storage, "counter").unwrap();
+
+    manager.modify_store(|store| store.count = 1).unwrap();
+
+    assert!(!manager.can_undo());
+    assert!(!manager.undo().unwrap());
+
+    manager.enable_history(10);
+    manager.modify_store(|store| store.count = 2).unwrap();
+    manager.disable_history();
+    assert!(!manager.undo().unwrap());
+    assert_eq!(manager.get_store().count, 2);
+}
@@ -0,0 +1,176 @@
+mod common;
+
+use std::fs;
+
+use rusty_store::{RecoveryPolicy, StoreManager, Storing};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Storing)]
+pub struct Counter {
+    pub count: u32,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct Disposable {
+    pub count: u32,
+}
+
+impl Storing for Disposable {
+    fn recovery_policy() -> Option<RecoveryPolicy> {
+        Some(RecoveryPolicy::MoveAside)
+    }
+}
+
+#[test]
+fn writes_files_with_the_format_extension() {
+    let (dir, storage) = common::storage();
+    let mut manager = StoreManager::<Counter>::new(&storage, "counter").unwrap();
+
+    manager.modify_store(|store| store.count = 1).unwrap();
+
+    assert_eq!(
+        common::entries(&dir.path().join("data")),
+        vec!["counter.ron"]
+    );
+}
+
+#[test]
+fn migrates_extensionless_files() {
+    let (dir, storage) = common::storage();
+    fs::create_dir_all(dir.path().join("data")).unwrap();
+    fs::write(dir.path().join("data/counter"), "(count: 7)").unwrap();
+
+    let manager = StoreManager::<Counter>::new(&storage, "counter").unwrap();
+
+    assert_eq!(manager.get_store().count, 7);
+    assert_eq!(
+        common::entries(&dir.path().join("data")),
+        vec!["counter.ron"]
+    );
+    assert_eq!(
+        fs::read_to_string(dir.path().join("data/counter.ron")).unwrap(),
+        "(count: 7)"
+    );
+}
+
+#[test]
+fn broken_extensionless_files_are_recovered_after_migration() {
+    let (dir, storage) = common::storage();
+    fs::create_dir_all(dir.path().join("data")).unwrap();
+    fs::write(dir.path().join("data/disposable"), "(count: ").unwrap();
+
+    let manager = StoreManager::<Disposable>::new(&storage, "disposable").unwrap();
+
+    assert_eq!(manager.get_store().count, 0);
+    let entries = common::entries(&dir.path().join("data"));
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0], "disposable.ron");
+    assert!(entries[1].starts_with("disposable.ron.corrupt-"));
+}
+
+#[test]
+fn current_file_takes_precedence() {
+    let (dir, storage) = common::storage();
+    fs::create_dir_all(dir.path().join("data")).unwrap();
+    fs::write(dir.path().join("data/counter"), "(count: 7)").unwrap();
+    fs::write(dir.path().join("data/counter.ron"), "(count: 8)").unwrap();
+
+    let manager = StoreManager::<Counter>::new(&storage, "counter").unwrap();
+
+    assert_eq!(manager.get_store().count, 8);
+    assert!(dir.path().join("data/counter").exists());
+}
+
+#[cfg(feature = "json")]
+mod json {
+    use super::*;
+    use rusty_store::{Format, StoreError};
+
+    #[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+    pub struct JsonCounter {
+        pub count: u32,
+    }
+
+    impl Storing for JsonCounter {
+        fn format() -> Format {
+            Format::Json
+        }
+    }
+
+    #[test]
+    fn converts_files_of_another_format() {
+        let (dir, storage) = common::storage();
+        fs::create_dir_all(dir.path().join("data")).unwrap();
+        fs::write(dir.path().join("data/counter.ron"), "(count: 3)").unwrap();
+
+        let manager = StoreManager::<JsonCounter>::new(&storage, "counter").unwrap();
+
+        assert_eq!(manager.get_store().count, 3);
+        assert_eq!(
+            common::entries(&dir.path().join("data")),
+            vec!["counter.json"]
+        );
+        let contents = fs::read_to_string(dir.path().join("data/counter.json")).unwrap();
+        assert!(contents.contains("\"count\": 3"), "{}", contents);
+    }
+
+    #[test]
+    fn converts_extensionless_ron_files() {
+        let (dir, storage) = common::storage();
+        fs::create_dir_all(dir.path().join("data")).unwrap();
+        fs::write(dir.path().join("data/counter"), "(count: 4)").unwrap();
+
+        let manager = StoreManager::<JsonCounter>::new(&storage, "counter").unwrap();
+
+        assert_eq!(manager.get_store().count, 4);
+        assert_eq!(
+            common::entries(&dir.path().join("data")),
+            vec!["counter.json"]
+        );
+    }
+
+    #[test]
+    fn keeps_files_of_another_format_which_cannot_be_parsed() {
+        let (dir, storage) = common::storage();
+        fs::create_dir_all(dir.path().join("data")).unwrap();
+        fs::write(dir.path().join("data/counter.ron"), "(count: ").unwrap();
+
+        let result = StoreManager::<JsonCounter>::new(&storage, "counter");
+
+        assert!(matches!(result, Err(StoreError::RonParse(_))));
+        assert_eq!(
+            common::entries(&dir.path().join("data")),
+            vec!["counter.ron"]
+        );
+    }
+
+    #[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+    pub struct JsonDisposable {
+        pub count: u32,
+    }
+
+    impl Storing for JsonDisposable {
+        fn format() -> Format {
+            Format::Json
+        }
+
+        fn recovery_policy() -> Option<RecoveryPolicy> {
+            Some(RecoveryPolicy::MoveAside)
+        }
+    }
+
+    #[test]
+    fn unreadable_files_of_another_format_do_not_prevent_recovery() {
+        let (dir, storage) = common::storage();
+        fs::create_dir_all(dir.path().join("data")).unwrap();
+        fs::write(dir.path().join("data/counter.ron"), "(count: ").unwrap();
+
+        let manager = StoreManager::<JsonDisposable>::new(&storage, "counter").unwrap();
+
+        assert_eq!(manager.get_store().count, 0);
+        assert_eq!(
+            common::entries(&dir.path().join("data")),
+            vec!["counter.json", "counter.ron"]
+        );
+    }
+}
@@ -0,0 +1,82 @@
+mod common;
+
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use rusty_store::{StoreError, StoreHandle, StoreManager, Storing};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default, Debug, Storing)]
+pub struct Counter {
+    pub count: u32,
+}
+
+#[test]
+fn exclusive_lock_blocks_other_writers_until_timeout() {
+    let (_dir, storage) = common::storage();
+    let storage = storage.with_lock_timeout(Duration::from_millis(50));
+
+    let mut manager = StoreManager::<Counter>::new(&storage, "counter").unwrap();
+    let mut locked = manager.lock_exclusive().unwrap();
+    locked.modify_store(|store| store.count += 1).unwrap();
+
+    let (sender, receiver) = mpsc::channel();
+    let writer = {
+        let storage = storage.clone();
+        thread::spawn(move || {
+            let mut handle = StoreHandle::<Counter>::new("counter");
+            sender.send(storage.write(&mut handle)).unwrap();
+        })
+    };
+    writer.join().unwrap();
+
+    let result = receiver.recv().unwrap();
+    assert!(
+        matches!(result, Err(StoreError::Lock(err)) if err.kind() == std::io::ErrorKind::TimedOut)
+    );
+
+    drop(locked);
+
+    let mut handle = StoreHandle::<Counter>::new("counter");
+    storage.read(&mut handle).unwrap();
+    assert_eq!(handle.get_store().count, 1);
+}
+
+#[test]
+fn exclusive_lock_prevents_lost_updates() {
+    let (_dir, storage) = common::storage();
+
+    let workers: Vec<_> = (0..4)
+        .map(|_| {
+            let storage = storage.clone();
+            thread::spawn(move || {
+                let mut manager = StoreManager::<Counter>::new(&storage, "counter").unwrap();
+                for _ in 0..25 {
+                    let mut locked = manager.lock_exclusive().unwrap();
+                    locked.modify_store(|store| store.count += 1).unwrap();
+                }
+            })
+        })
+        .collect();
+
+    for worker in workers {
+        worker.join().unwrap();
+    }
+
+    let mut manager = StoreManager::<Counter>::new(&storage, "counter").unwrap();
+    assert_eq!(manager.get_store_alive().unwrap().count, 100);
+}
+
+#[test]
+fn lock_is_released_on_drop() {
+    let (_dir, storage) = common::storage();
+    let storage = storage.with_lock_timeout(Duration::from_millis(50));
+
+    let mut manager = StoreManager::<Counter>::new(&storage, "counter").unwrap();
+    drop(manager.lock_exclusive().unwrap());
+
+    let mut handle = StoreHandle::<Counter>::new("counter");
+    storage.write(&mut handle).unwrap();
+    manager.save().unwrap();
+}
@@ -0,0 +1,261 @@
+mod common;
+
+use std::collections::BTreeMap;
+use std::fs;
+
+use rusty_store::{Migrations, StoreError, StoreManager, Storing, StoringType, Value};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Clone)]
+pub enum Theme {
+    #[default]
+    Light,
+    Dark,
+    Custom(u8),
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+pub struct Settings {
+    pub theme: Theme,
+    pub accent: Theme,
+    pub font_size: u32,
+}
+
+impl Storing for Settings {
+    const VERSION: u32 = 2;
+
+    fn store_type() -> StoringType {
+        StoringType::Config
+    }
+
+    fn migrations() -> Migrations {
+        Migrations::new()
+            .step(1, |store| {
+                store.rename("color_scheme", "theme");
+                Ok(())
+            })
+            .step(2, |store| {
+                store.insert("font_size", Value::from(12));
+                Ok(())
+            })
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+pub struct Strict {
+    pub count: u32,
+}
+
+impl Storing for Strict {
+    const VERSION: u32 = 1;
+
+    fn migrations() -> Migrations {
+        Migrations::new().step(1, |_| Err("count cannot be recovered".to_owned()))
+    }
+}
+
+fn write_file(dir: &tempfile::TempDir, name: &str, contents: &str) -> std::path::PathBuf {
+    let parent = dir.path().join(if name.starts_with("settings") {
+        "config"
+    } else {
+        "data"
+    });
+    fs::create_dir_all(&parent).unwrap();
+    let path = parent.join(name);
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+const UNVERSIONED: &str = "(color_scheme: Dark, accent: Custom(3))";
+
+#[test]
+fn unversioned_files_are_migrated_and_rewritten() {
+    let (dir, storage) = common::storage();
+    let path = write_file(&dir, "settings.ron", UNVERSIONED);
+
+    let manager = StoreManager::<Settings>::new(&storage, "settings").unwrap();
+
+    let expected = Settings {
+        theme: Theme::Dark,
+        accent: Theme::Custom(3),
+        font_size: 12,
+    };
+    assert_eq!(*manager.get_store(), expected);
+    let contents = fs::read_to_string(&path).unwrap();
+    assert!(contents.contains("version: 2"), "{}", contents);
+    let reloaded = StoreManager::<Settings>::new(&storage, "settings").unwrap();
+    assert_eq!(*reloaded.get_store(), expected);
+}
+
+#[test]
+fn migrated_files_are_backed_up() {
+    let (dir, storage) = common::storage();
+    write_file(&dir, "settings.ron", UNVERSIONED);
+
+    StoreManager::<Settings>::new(&storage, "settings").unwrap();
+
+    assert_eq!(
+        common::entries(&dir.path().join("config")),
+        vec!["settings.ron", "settings.ron.v0.bak"]
+    );
+    let backup = fs::read_to_string(dir.path().join("config/settings.ron.v0.bak")).unwrap();
+    assert_eq!(backup, UNVERSIONED);
+}
+
+#[test]
+fn only_missing_steps_run() {
+    let (dir, storage) = common::storage();
+    write_file(
+        &dir,
+        "settings.ron",
+        "(version: 1, store: (theme: Light, accent: Dark))",
+    );
+
+    let manager = StoreManager::<Settings>::new(&storage, "settings").unwrap();
+
+    assert_eq!(manager.get_store().theme, Theme::Light);
+    assert_eq!(manager.get_store().font_size, 12);
+    assert!(dir.path().join("config/settings.ron.v1.bak").exists());
+}
+
+#[test]
+fn current_files_are_not_rewritten() {
+    let (dir, storage) = common::storage();
+    let mut manager = StoreManager::<Settings>::new(&storage, "settings").unwrap();
+    manager.modify_store(|store| store.font_size = 14).unwrap();
+
+    let reloaded = StoreManager::<Settings>::new(&storage, "settings").unwrap();
+
+    assert_eq!(reloaded.get_store().font_size, 14);
+    assert_eq!(
+        common::entries(&dir.path().join("config")),
+        vec!["settings.ron"]
+    );
+}
+
+#[test]
+fn newer_versions_are_rejected() {
+    let (dir, storage) = common::storage();
+    let path = write_file(
+        &dir,
+        "settings.ron",
+        "(version: 3, store: (theme: Light, accent: Dark, font_size: 1))",
+    );
+
+    let result = StoreManager::<Settings>::new(&storage, "settings");
+
+    assert!(matches!(
+        result,
+        Err(StoreError::UnsupportedVersion {
+            version: 3,
+            supported: 2
+        })
+    ));
+    assert!(fs::read_to_string(&path).unwrap().contains("version: 3"));
+}
+
+#[test]
+fn failed_steps_leave_the_file_untouched() {
+    let (dir, storage) = common::storage();
+    let path = write_file(&dir, "strict.ron", "(total: 3)");
+
+    let result = StoreManager::<Strict>::new(&storage, "strict");
+
+    match result {
+        Err(StoreError::Migration { version, message }) => {
+            assert_eq!(version, 1);
+            assert_eq!(message, "count cannot be recovered");
+        }
+        other => panic!("unexpected result: {:?}", other.map(|_| ())),
+    }
+    assert_eq!(fs::read_to_string(&path).unwrap(), "(total: 3)");
+    assert_eq!(
+        common::entries(&dir.path().join("data")),
+        vec!["strict.ron"]
+    );
+}
+
+#[test]
+fn broken_current_files_are_parse_errors() {
+    let (dir, storage) = common::storage();
+    write_file(
+        &dir,
+        "strict.ron",
+        "(version: 1, store: (count: \"three\"))",
+    );
+
+    let result = StoreManager::<Strict>::new(&storage, "strict");
+
+    assert!(matches!(result, Err(StoreError::RonParse(_))));
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+pub struct Tagged {
+    pub color: String,
+    pub tags: BTreeMap<String, u32>,
+}
+
+impl Storing for Tagged {
+    const VERSION: u32 = 1;
+}
+
+#[test]
+fn maps_are_migrated() {
+    let (dir, storage) = common::storage();
+    write_file(
+        &dir,
+        "tagged.ron",
+        "(color: \"dark\", tags: {\"a\": 1, \"b\": 2})",
+    );
+
+    let manager = StoreManager::<Tagged>::new(&storage, "tagged").unwrap();
+
+    let tags = BTreeMap::from([("a".to_owned(), 1), ("b".to_owned(), 2)]);
+    assert_eq!(manager.get_store().tags, tags);
+    assert!(dir.path().join("data/tagged.ron.v0.bak").exists());
+}
+
+#[cfg(feature = "json")]
+mod json {
+    use super::*;
+    use rusty_store::Format;
+
+    #[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+    pub struct JsonSettings {
+        pub theme: Theme,
+        pub font_size: u32,
+    }
+
+    impl Storing for JsonSettings {
+        const VERSION: u32 = 1;
+
+        fn format() -> Format {
+            Format::Json
+        }
+
+        fn migrations() -> Migrations {
+            Migrations::new().step(1, |store| {
+                store.rename("color_scheme", "theme");
+                store.insert("font_size", Value::from(12));
+                Ok(())
+            })
+        }
+    }
+
+    #[test]
+    fn unversioned_files_are_migrated() {
+        let (dir, storage) = common::storage();
+        let path = write_file(
+            &dir,
+            "json_settings.json",
+            r#"{"color_scheme": {"Custom": 7}}"#,
+        );
+
+        let manager = StoreManager::<JsonSettings>::new(&storage, "json_settings").unwrap();
+
+        assert_eq!(manager.get_store().theme, Theme::Custom(7));
+        assert_eq!(manager.get_store().font_size, 12);
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(contents.contains("\"version\""), "{}", contents);
+    }
+}
@@ -0,0 +1,397 @@
+mod common;
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use rusty_store::{MissingFields, StoreError, StoreManager, Storing, StoringType};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Window {
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Settings {
+    pub theme: String,
+    pub font_size: u32,
+    pub window: Window,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            theme: "light".to_owned(),
+            font_size: 12,
+            window: Window {
+                width: 800,
+                height: 600,
+            },
+        }
+    }
+}
+
+impl Storing for Settings {
+    fn store_type() -> StoringType {
+        StoringType::Config
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+pub struct Counter {
+    pub count: u32,
+    pub step: u32,
+}
+
+impl Storing for Counter {
+    fn missing_fields() -> Option<MissingFields> {
+        Some(MissingFields::FillAndRewrite)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Versioned {
+    pub name: String,
+    pub limit: u32,
+}
+
+impl Default for Versioned {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            limit: 10,
+        }
+    }
+}
+
+impl Storing for Versioned {
+    const VERSION: u32 = 1;
+
+    fn missing_fields() -> Option<MissingFields> {
+        Some(MissingFields::Fill)
+    }
+}
+
+fn write_settings(dir: &tempfile::TempDir, contents: &str) -> PathBuf {
+    fs::create_dir_all(dir.path().join("config")).unwrap();
+    let path = dir.path().join("config/settings.ron");
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn missing_fields_fail_by_default() {
+    let (dir, storage) = common::storage();
+    write_settings(&dir, "(theme: \"dark\")");
+
+    let result = StoreManager::<Settings>::new(&storage, "settings");
+
+    assert!(matches!(result, Err(StoreError::RonParse(_))));
+}
+
+#[test]
+fn missing_fields_are_filled_from_default() {
+    let (dir, storage) = common::storage();
+    let storage = storage.with_missing_fields(MissingFields::Fill);
+    let path = write_settings(&dir, "(theme: \"dark\", window: (width: 1024))");
+
+    let manager = StoreManager::<Settings>::new(&storage, "settings").unwrap();
+
+    assert_eq!(
+        *manager.get_store(),
+        Settings {
+            theme: "dark".to_owned(),
+            font_size: 12,
+            window: Window {
+                width: 1024,
+                height: 600,
+            },
+        }
+    );
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "(theme: \"dark\", window: (width: 1024))"
+    );
+}
+
+#[test]
+fn filled_files_are_rewritten_on_request() {
+    let (dir, storage) = common::storage();
+    let storage = storage.with_missing_fields(MissingFields::FillAndRewrite);
+    let path = write_settings(&dir, "(theme: \"dark\")");
+
+    StoreManager::<Settings>::new(&storage, "settings").unwrap();
+
+    let contents = fs::read_to_string(&path).unwrap();
+    assert!(contents.contains("font_size: 12"), "{}", contents);
+    assert!(contents.contains("height: 600"), "{}", contents);
+    let strict = storage.with_missing_fields(MissingFields::Fail);
+    let reloaded = StoreManager::<Settings>::new(&strict, "settings").unwrap();
+    assert_eq!(reloaded.get_store().theme, "dark");
+}
+
+#[test]
+fn stores_override_the_storage() {
+    let (dir, storage) = common::storage();
+    fs::create_dir_all(dir.path().join("data")).unwrap();
+    let path = dir.path().join("data/counter.ron");
+    fs::write(&path, "(count: 4)").unwrap();
+
+    let manager = StoreManager::<Counter>::new(&storage, "counter").unwrap();
+
+    assert_eq!(manager.get_store().count, 4);
+    let contents = fs::read_to_string(&path).unwrap();
+    assert!(contents.contains("step: 0"), "{}", contents);
+}
+
+#[test]
+fn broken_files_are_still_parse_errors() {
+    let (dir, storage) = common::storage();
+    let storage = storage.with_missing_fields(MissingFields::FillAndRewrite);
+    let path = write_settings(&dir, "(theme: 3)");
+
+    let result = StoreManager::<Settings>::new(&storage, "settings");
+
+    assert!(matches!(result, Err(StoreError::RonParse(_))));
+    assert_eq!(fs::read_to_string(&path).unwrap(), "(theme: 3)");
+}
+
+#[test]
+fn versioned_stores_are_filled() {
+    let (dir, storage) = common::storage();
+    fs::create_dir_all(dir.path().join("data")).unwrap();
+    fs::write(
+        dir.path().join("data/versioned.ron"),
+        "(version: 1, store: (name: \"a\"))",
+    )
+    .unwrap();
+
+    let manager = StoreManager::<Versioned>::new(&storage, "versioned").unwrap();
+
+    assert_eq!(
+        *manager.get_store(),
+        Versioned {
+            name: "a".to_owned(),
+            limit: 10,
+        }
+    );
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Plugins {
+    pub plugins: BTreeMap<String, bool>,
+    pub added: u32,
+}
+
+impl Default for Plugins {
+    fn default() -> Self {
+        Self {
+            plugins: BTreeMap::from([("spell".to_owned(), true), ("lint".to_owned(), true)]),
+            added: 5,
+        }
+    }
+}
+
+impl Storing for Plugins {
+    fn missing_fields() -> Option<MissingFields> {
+        Some(MissingFields::FillAndRewrite)
+    }
+}
+
+#[test]
+fn map_entries_are_not_filled() {
+    let (dir, storage) = common::storage();
+    fs::create_dir_all(dir.path().join("data")).unwrap();
+    let path = dir.path().join("data/plugins.ron");
+    fs::write(&path, "(plugins: {\"spell\": false})").unwrap();
+
+    let manager = StoreManager::<Plugins>::new(&storage, "plugins").unwrap();
+
+    assert_eq!(
+        manager.get_store().plugins,
+        BTreeMap::from([("spell".to_owned(), false)])
+    );
+    assert_eq!(manager.get_store().added, 5);
+    let contents = fs::read_to_string(&path).unwrap();
+    assert!(!contents.contains("lint"), "{}", contents);
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub enum Mode {
+    A { x: u32, z: u32 },
+    B { y: u32 },
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Modal {
+    pub mode: Mode,
+    pub added: u32,
+}
+
+impl Default for Modal {
+    fn default() -> Self {
+        Self {
+            mode: Mode::A { x: 1, z: 2 },
+            added: 5,
+        }
+    }
+}
+
+impl Storing for Modal {
+    fn missing_fields() -> Option<MissingFields> {
+        Some(MissingFields::Fill)
+    }
+}
+
+#[test]
+fn other_enum_variants_are_not_filled() {
+    let (dir, storage) = common::storage();
+    fs::create_dir_all(dir.path().join("data")).unwrap();
+    fs::write(dir.path().join("data/modal.ron"), "(mode: B(y: 3))").unwrap();
+
+    let manager = StoreManager::<Modal>::new(&storage, "modal").unwrap();
+
+    assert_eq!(
+        *manager.get_store(),
+        Modal {
+            mode: Mode::B { y: 3 },
+            added: 5,
+        }
+    );
+}
+
+#[test]
+fn default_enum_variant_is_filled() {
+    let (dir, storage) = common::storage();
+    fs::create_dir_all(dir.path().join("data")).unwrap();
+    fs::write(dir.path().join("data/modal.ron"), "(mode: A(x: 3))").unwrap();
+
+    let manager = StoreManager::<Modal>::new(&storage, "modal").unwrap();
+
+    assert_eq!(
+        *manager.get_store(),
+        Modal {
+            mode: Mode::A { x: 3, z: 2 },
+            added: 5,
+        }
+    );
+}
+
+#[cfg(feature = "json")]
+mod json {
+    use super::*;
+    use rusty_store::Format;
+
+    #[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+    pub struct JsonCounter {
+        pub count: u32,
+        pub step: u32,
+    }
+
+    impl Storing for JsonCounter {
+        fn format() -> Format {
+            Format::Json
+        }
+
+        fn missing_fields() -> Option<MissingFields> {
+            Some(MissingFields::FillAndRewrite)
+        }
+    }
+
+    #[test]
+    fn missing_fields_are_filled() {
+        let (dir, storage) = common::storage();
+        fs::create_dir_all(dir.path().join("data")).unwrap();
+        let path = dir.path().join("data/counter.json");
+        fs::write(&path, r#"{"count": 4}"#).unwrap();
+
+        let manager = StoreManager::<JsonCounter>::new(&storage, "counter").unwrap();
+
+        assert_eq!(manager.get_store().count, 4);
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(contents.contains("\"step\""), "{}", contents);
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    pub struct JsonPlugins {
+        pub plugins: BTreeMap<String, bool>,
+        pub added: u32,
+    }
+
+    impl Default for JsonPlugins {
+        fn default() -> Self {
+            let Plugins { plugins, added } = Plugins::default();
+            Self { plugins, added }
+        }
+    }
+
+    impl Storing for JsonPlugins {
+        fn format() -> Format {
+            Format::Json
+        }
+
+        fn missing_fields() -> Option<MissingFields> {
+            Some(MissingFields::Fill)
+        }
+    }
+
+    #[test]
+    fn map_entries_are_not_filled() {
+        let (dir, storage) = common::storage();
+        fs::create_dir_all(dir.path().join("data")).unwrap();
+        fs::write(
+            dir.path().join("data/plugins.json"),
+            r#"{"plugins": {"spell": false}}"#,
+        )
+        .unwrap();
+
+        let manager = StoreManager::<JsonPlugins>::new(&storage, "plugins").unwrap();
+
+        assert_eq!(
+            manager.get_store().plugins,
+            BTreeMap::from([("spell".to_owned(), false)])
+        );
+        assert_eq!(manager.get_store().added, 5);
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    pub struct JsonModal {
+        pub mode: Mode,
+        pub added: u32,
+    }
+
+    impl Default for JsonModal {
+        fn default() -> Self {
+            let Modal { mode, added } = Modal::default();
+            Self { mode, added }
+        }
+    }
+
+    impl Storing for JsonModal {
+        fn format() -> Format {
+            Format::Json
+        }
+
+        fn missing_fields() -> Option<MissingFields> {
+            Some(MissingFields::Fill)
+        }
+    }
+
+    #[test]
+    fn enum_variants_are_not_filled() {
+        let (dir, storage) = common::storage();
+        fs::create_dir_all(dir.path().join("data")).unwrap();
+        fs::write(
+            dir.path().join("data/modal.json"),
+            r#"{"mode": {"B": {"y": 1}}}"#,
+        )
+        .unwrap();
+
+        let manager = StoreManager::<JsonModal>::new(&storage, "modal").unwrap();
+
+        assert_eq!(manager.get_store().mode, Mode::B { y: 1 });
+        assert_eq!(manager.get_store().added, 5);
+    }
+}
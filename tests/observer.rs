@@ -0,0 +1,130 @@
+mod common;
+
+use std::collections::HashMap;
+use std::fs;
+use std::sync::{Arc, Mutex};
+
+use rusty_store::{StoreManager, Storing};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default, Debug, Storing)]
+pub struct Settings {
+    pub theme: String,
+}
+
+type Changes = Arc<Mutex<Vec<(String, String)>>>;
+
+fn observe(manager: &mut StoreManager<Settings>) -> (Changes, rusty_store::Subscription) {
+    let changes = Changes::default();
+    let subscription = {
+        let changes = changes.clone();
+        manager.subscribe(move |old, new| {
+            changes
+                .lock()
+                .unwrap()
+                .push((old.theme.clone(), new.theme.clone()))
+        })
+    };
+    (changes, subscription)
+}
+
+fn change(old: &str, new: &str) -> (String, String) {
+    (old.to_owned(), new.to_owned())
+}
+
+#[test]
+fn notified_after_committed_modifications() {
+    let (_dir, storage) = common::storage();
+    let mut manager = StoreManager::<Settings>::new(&storage, "settings").unwrap();
+    let (changes, _) = observe(&mut manager);
+
+    manager
+        .modify_store(|store| store.theme = "dark".to_owned())
+        .unwrap();
+    manager.modify_store_uncommitted(|store| store.theme = "light".to_owned());
+    assert_eq!(changes.lock().unwrap().len(), 1);
+
+    manager.save().unwrap();
+    manager.edit().theme = "blue".to_owned();
+
+    assert_eq!(
+        *changes.lock().unwrap(),
+        vec![
+            change("", "dark"),
+            change("dark", "light"),
+            change("light", "blue"),
+        ]
+    );
+}
+
+#[test]
+fn not_notified_without_changes_or_failed_saves() {
+    let (dir, storage) = common::storage();
+    let mut manager = StoreManager::<Settings>::new(&storage, "settings").unwrap();
+    let (changes, _) = observe(&mut manager);
+
+    manager.save().unwrap();
+    manager.get_store_alive().unwrap();
+
+    fs::write(dir.path().join("data/settings.ron"), "(theme: \"red\")").unwrap();
+    assert!(manager
+        .modify_store(|store| store.theme = "dark".to_owned())
+        .is_err());
+
+    assert!(changes.lock().unwrap().is_empty());
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Storing)]
+pub struct Shortcuts {
+    pub keys: HashMap<String, u32>,
+}
+
+#[test]
+fn not_notified_when_reloads_reorder_maps() {
+    let (_dir, storage) = common::storage();
+    let mut manager = StoreManager::<Shortcuts>::new(&storage, "shortcuts").unwrap();
+    manager
+        .modify_store(|store| {
+            store.keys = (0..32).map(|key| (format!("key{}", key), key)).collect();
+        })
+        .unwrap();
+
+    let calls = Arc::new(Mutex::new(0));
+    {
+        let calls = calls.clone();
+        manager.subscribe(move |_, _| *calls.lock().unwrap() += 1);
+    }
+    for _ in 0..5 {
+        manager.get_store_alive().unwrap();
+    }
+
+    assert_eq!(*calls.lock().unwrap(), 0);
+}
+
+#[test]
+fn notified_when_reload_finds_changes() {
+    let (dir, storage) = common::storage();
+    let mut manager = StoreManager::<Settings>::new(&storage, "settings").unwrap();
+    let (changes, _) = observe(&mut manager);
+
+    fs::write(dir.path().join("data/settings.ron"), "(theme: \"red\")").unwrap();
+    manager.get_store_alive().unwrap();
+    manager.get_store_alive().unwrap();
+
+    assert_eq!(*changes.lock().unwrap(), vec![change("", "red")]);
+}
+
+#[test]
+fn unsubscribed_callbacks_are_not_called() {
+    let (_dir, storage) = common::storage();
+    let mut manager = StoreManager::<Settings>::new(&storage, "settings").unwrap();
+    let (changes, subscription) = observe(&mut manager);
+
+    assert!(manager.unsubscribe(subscription));
+    assert!(!manager.unsubscribe(subscription));
+
+    manager
+        .modify_store(|store| store.theme = "dark".to_owned())
+        .unwrap();
+    assert!(changes.lock().unwrap().is_empty());
+}
@@ -0,0 +1,220 @@
+mod common;
+
+use std::fs;
+
+use rusty_store::{StoreManager, Storing, StoringType};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Clone)]
+pub struct Window {
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+pub struct Settings {
+    pub theme: String,
+    pub window: Window,
+    pub recent: Vec<String>,
+    #[serde(default)]
+    pub volume: u8,
+}
+
+impl Storing for Settings {
+    fn store_type() -> StoringType {
+        StoringType::Config
+    }
+
+    fn preserve_formatting() -> bool {
+        true
+    }
+}
+
+const HAND_EDITED: &str = r#"// Settings of the app, edited by hand.
+(
+    theme: "dark", // or "light"
+    /* The main window. */
+    window: (width: 800,   height: 600),
+    recent: [
+        "a.txt",
+    ],
+)
+"#;
+
+fn write_settings(dir: &tempfile::TempDir, contents: &str) -> std::path::PathBuf {
+    fs::create_dir_all(dir.path().join("config")).unwrap();
+    let path = dir.path().join("config/settings.ron");
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn only_changed_values_are_rewritten() {
+    let (dir, storage) = common::storage();
+    let path = write_settings(&dir, HAND_EDITED);
+    let mut manager = StoreManager::<Settings>::new(&storage, "settings").unwrap();
+
+    manager
+        .modify_store(|store| {
+            store.theme = "light".to_owned();
+            store.window.height = 720;
+        })
+        .unwrap();
+
+    // The added `volume` field goes after the last one.
+    let expected = HAND_EDITED
+        .replace("\"dark\"", "\"light\"")
+        .replace("600", "720")
+        .replace("    ],\n", "    ],\n    volume: 0,\n");
+    assert_eq!(fs::read_to_string(&path).unwrap(), expected);
+}
+
+#[test]
+fn unchanged_stores_keep_their_file() {
+    let (dir, storage) = common::storage();
+    let path = write_settings(&dir, &HAND_EDITED.replace(")\n", "    volume: 3,\n)\n"));
+    let mut manager = StoreManager::<Settings>::new(&storage, "settings").unwrap();
+    let before = fs::read_to_string(&path).unwrap();
+
+    manager.modify_store(|store| store.volume = 3).unwrap();
+
+    assert_eq!(fs::read_to_string(&path).unwrap(), before);
+}
+
+#[test]
+fn removed_fields_are_deleted_with_their_comments() {
+    let (dir, storage) = common::storage();
+    let contents = HAND_EDITED.replace(
+        "    recent",
+        "    // No longer used.\n    legacy: true,\n    recent",
+    );
+    let path = write_settings(&dir, &contents);
+    let mut manager = StoreManager::<Settings>::new(&storage, "settings").unwrap();
+
+    manager.modify_store(|store| store.volume = 5).unwrap();
+
+    let expected = HAND_EDITED.replace("    ],\n", "    ],\n    volume: 5,\n");
+    assert_eq!(fs::read_to_string(&path).unwrap(), expected);
+}
+
+#[test]
+fn single_line_files_stay_on_one_line() {
+    let (dir, storage) = common::storage();
+    let contents = "(theme: \"dark\", window: (width: 1, height: 2), recent: [])";
+    let path = write_settings(&dir, contents);
+    let mut manager = StoreManager::<Settings>::new(&storage, "settings").unwrap();
+
+    manager.modify_store(|store| store.volume = 2).unwrap();
+
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "(theme: \"dark\", window: (width: 1, height: 2), recent: [], volume: 2)"
+    );
+}
+
+#[test]
+fn resized_lists_are_rewritten() {
+    let (dir, storage) = common::storage();
+    let path = write_settings(&dir, HAND_EDITED);
+    let mut manager = StoreManager::<Settings>::new(&storage, "settings").unwrap();
+
+    manager
+        .modify_store(|store| store.recent.push("b.txt".to_owned()))
+        .unwrap();
+
+    let contents = fs::read_to_string(&path).unwrap();
+    assert!(
+        contents.starts_with("// Settings of the app"),
+        "{}",
+        contents
+    );
+    assert!(contents.contains("// or \"light\""), "{}", contents);
+    let reloaded = StoreManager::<Settings>::new(&storage, "settings").unwrap();
+    assert_eq!(reloaded.get_store().recent, vec!["a.txt", "b.txt"]);
+}
+
+#[test]
+fn new_files_are_written_whole() {
+    let (dir, storage) = common::storage();
+    let mut manager = StoreManager::<Settings>::new(&storage, "settings").unwrap();
+
+    manager.modify_store(|store| store.volume = 1).unwrap();
+
+    let contents = fs::read_to_string(dir.path().join("config/settings.ron")).unwrap();
+    assert!(contents.contains("volume: 1"), "{}", contents);
+}
+
+#[cfg(feature = "toml")]
+mod toml {
+    use super::*;
+    use rusty_store::Format;
+
+    #[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+    pub struct TomlSettings {
+        pub theme: String,
+        pub window: Window,
+        #[serde(default)]
+        pub volume: u8,
+    }
+
+    impl Storing for TomlSettings {
+        fn store_type() -> StoringType {
+            StoringType::Config
+        }
+
+        fn format() -> Format {
+            Format::Toml
+        }
+
+        fn preserve_formatting() -> bool {
+            true
+        }
+    }
+
+    const HAND_EDITED: &str = r#"# Settings of the app, edited by hand.
+theme = "dark" # or "light"
+
+# The main window.
+[window]
+width  = 800
+height = 600
+"#;
+
+    #[test]
+    fn only_changed_values_are_rewritten() {
+        let (dir, storage) = common::storage();
+        fs::create_dir_all(dir.path().join("config")).unwrap();
+        let path = dir.path().join("config/settings.toml");
+        fs::write(&path, HAND_EDITED).unwrap();
+        let mut manager = StoreManager::<TomlSettings>::new(&storage, "settings").unwrap();
+
+        manager
+            .modify_store(|store| {
+                store.theme = "light".to_owned();
+                store.window.height = 720;
+            })
+            .unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(
+            contents.starts_with("# Settings of the app"),
+            "{}",
+            contents
+        );
+        assert!(
+            contents.contains("theme = \"light\" # or \"light\""),
+            "{}",
+            contents
+        );
+        assert!(
+            contents.contains("# The main window.\n[window]"),
+            "{}",
+            contents
+        );
+        assert!(contents.contains("width  = 800\n"), "{}", contents);
+        assert!(contents.contains("height = 720\n"), "{}", contents);
+        let reloaded = StoreManager::<TomlSettings>::new(&storage, "settings").unwrap();
+        assert_eq!(reloaded.get_store().window.height, 720);
+        assert_eq!(reloaded.get_store().volume, 0);
+    }
+}
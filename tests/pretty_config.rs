@@ -0,0 +1,97 @@
+mod common;
+
+use std::fs;
+
+use rusty_store::{StoreManager, Storing};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Clone)]
+pub struct Meters(pub u32);
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Storing)]
+pub struct Plain {
+    pub values: Vec<u32>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Storing)]
+#[storing(indentor = "\t", struct_names, compact_arrays = false)]
+#[storing(implicit_some, unwrap_newtypes)]
+pub struct Custom {
+    pub values: Vec<u32>,
+    pub name: Option<String>,
+    pub distance: Meters,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Storing)]
+#[storing(depth_limit = 1)]
+pub struct Shallow {
+    pub nested: Vec<Vec<u32>>,
+}
+
+#[test]
+fn default_creation_and_writes_are_formatted_alike() {
+    let (dir, storage) = common::storage();
+    let path = dir.path().join("data/plain.ron");
+
+    let mut manager = StoreManager::<Plain>::new(&storage, "plain").unwrap();
+    let created = fs::read_to_string(&path).unwrap();
+    manager.save().unwrap();
+
+    assert_eq!(fs::read_to_string(&path).unwrap(), created);
+
+    manager
+        .modify_store(|store| store.values = vec![1, 2, 3])
+        .unwrap();
+    let written = fs::read_to_string(&path).unwrap();
+    assert!(written.contains("values: [1, 2, 3]"), "{}", written);
+}
+
+#[test]
+fn attributes_configure_the_output() {
+    let (dir, storage) = common::storage();
+    let mut manager = StoreManager::<Custom>::new(&storage, "custom").unwrap();
+
+    manager
+        .modify_store(|store| {
+            store.values = vec![1, 2];
+            store.name = Some("home".to_owned());
+            store.distance = Meters(12);
+        })
+        .unwrap();
+
+    let contents = fs::read_to_string(dir.path().join("data/custom.ron")).unwrap();
+    assert!(
+        contents.contains("#![enable(implicit_some)]\n"),
+        "{}",
+        contents
+    );
+    assert!(
+        contents.contains("#![enable(unwrap_newtypes)]\n"),
+        "{}",
+        contents
+    );
+    assert!(contents.contains("Custom("), "{}", contents);
+    assert!(
+        contents.contains("\n\tvalues: [\n\t\t1,\n\t\t2,\n\t],"),
+        "{}",
+        contents
+    );
+    assert!(contents.contains("\tname: \"home\","), "{}", contents);
+    assert!(contents.contains("\tdistance: 12,"), "{}", contents);
+
+    let reloaded = StoreManager::<Custom>::new(&storage, "custom").unwrap();
+    assert_eq!(reloaded.get_store(), manager.get_store());
+}
+
+#[test]
+fn depth_limit_keeps_nested_values_on_one_line() {
+    let (dir, storage) = common::storage();
+    let mut manager = StoreManager::<Shallow>::new(&storage, "shallow").unwrap();
+
+    manager
+        .modify_store(|store| store.nested = vec![vec![1, 2], vec![3]])
+        .unwrap();
+
+    let contents = fs::read_to_string(dir.path().join("data/shallow.ron")).unwrap();
+    assert!(contents.contains("nested: [[1, 2], [3]]"), "{}", contents);
+}
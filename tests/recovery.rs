@@ -0,0 +1,167 @@
+mod common;
+
+use std::fs;
+use std::sync::{Arc, Mutex};
+
+use rusty_store::{Recovery, RecoveryPolicy, StoreError, StoreHandle, StoreManager, Storing};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default, Debug, Storing)]
+pub struct Counter {
+    pub count: u32,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct Disposable {
+    pub count: u32,
+}
+
+impl Storing for Disposable {
+    fn recovery_policy() -> Option<RecoveryPolicy> {
+        Some(RecoveryPolicy::MoveAside)
+    }
+}
+
+#[test]
+fn fails_by_default() {
+    let (dir, storage) = common::storage();
+    fs::create_dir_all(dir.path().join("data")).unwrap();
+    fs::write(dir.path().join("data/counter.ron"), "(count: ").unwrap();
+
+    let result = StoreManager::<Counter>::new(&storage, "counter");
+
+    assert!(matches!(result, Err(StoreError::RonParse(_))));
+    assert_eq!(
+        fs::read_to_string(dir.path().join("data/counter.ron")).unwrap(),
+        "(count: "
+    );
+}
+
+#[test]
+fn moves_broken_file_aside() {
+    let (dir, storage) = common::storage();
+    let recoveries = Arc::new(Mutex::new(Vec::new()));
+    let storage = {
+        let recoveries = recoveries.clone();
+        storage
+            .with_recovery_policy(RecoveryPolicy::MoveAside)
+            .on_recovery(move |recovery| {
+                recoveries
+                    .lock()
+                    .unwrap()
+                    .push(recovery.store_id().to_owned())
+            })
+    };
+    fs::create_dir_all(dir.path().join("data")).unwrap();
+    fs::write(dir.path().join("data/counter.ron"), "(count: ").unwrap();
+
+    let manager = StoreManager::<Counter>::new(&storage, "counter").unwrap();
+    assert_eq!(manager.get_store().count, 0);
+    assert_eq!(*recoveries.lock().unwrap(), vec!["counter"]);
+
+    let entries = common::entries(&dir.path().join("data"));
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0], "counter.ron");
+    assert!(entries[1].starts_with("counter.ron.corrupt-"));
+    assert_eq!(
+        fs::read_to_string(dir.path().join("data").join(&entries[1])).unwrap(),
+        "(count: "
+    );
+}
+
+#[test]
+fn store_policy_overrides_storage_policy() {
+    let (dir, storage) = common::storage();
+    fs::create_dir_all(dir.path().join("data")).unwrap();
+    fs::write(dir.path().join("data/disposable.ron"), "(count: ").unwrap();
+
+    let manager = StoreManager::<Disposable>::new(&storage, "disposable").unwrap();
+
+    assert_eq!(manager.get_store().count, 0);
+}
+
+#[test]
+fn restores_newest_readable_backup() {
+    let (dir, storage) = common::storage();
+    let recovered = Arc::new(Mutex::new(None));
+    let storage = {
+        let recovered = recovered.clone();
+        storage
+            .with_backups(2)
+            .with_recovery_policy(RecoveryPolicy::RestoreBackup)
+            .on_recovery(move |recovery| {
+                if let Recovery::RestoredBackup { backup, .. } = recovery {
+                    *recovered.lock().unwrap() = Some(backup.index());
+                }
+            })
+    };
+
+    let mut handle = StoreHandle::<Counter>::new("counter");
+    for count in [1, 2] {
+        handle.get_store_mut().count = count;
+        storage.write(&mut handle).unwrap();
+    }
+    fs::write(dir.path().join("data/counter.ron"), "(count: ").unwrap();
+
+    let manager = StoreManager::<Counter>::new(&storage, "counter").unwrap();
+
+    assert_eq!(manager.get_store().count, 1);
+    assert_eq!(*recovered.lock().unwrap(), Some(1));
+}
+
+#[test]
+fn restore_backup_without_backups_fails() {
+    let (dir, storage) = common::storage();
+    let storage = storage.with_recovery_policy(RecoveryPolicy::RestoreBackup);
+    fs::create_dir_all(dir.path().join("data")).unwrap();
+    fs::write(dir.path().join("data/counter.ron"), "(count: ").unwrap();
+
+    let result = StoreManager::<Counter>::new(&storage, "counter");
+
+    assert!(matches!(result, Err(StoreError::RonParse(_))));
+}
+
+#[test]
+fn broken_files_moved_aside_within_a_second_are_all_kept() {
+    let (dir, storage) = common::storage();
+    fs::create_dir_all(dir.path().join("data")).unwrap();
+    let path = dir.path().join("data/disposable.ron");
+
+    for contents in ["(count: ", "(count: 1"] {
+        fs::write(&path, contents).unwrap();
+        StoreManager::<Disposable>::new(&storage, "disposable").unwrap();
+    }
+
+    let corrupt: Vec<String> = common::entries(&dir.path().join("data"))
+        .into_iter()
+        .filter(|name| name.starts_with("disposable.ron.corrupt-"))
+        .collect();
+    assert_eq!(corrupt.len(), 2, "{:?}", corrupt);
+}
+
+#[test]
+fn concurrent_reads_recover_once() {
+    let (dir, storage) = common::storage();
+    fs::create_dir_all(dir.path().join("data")).unwrap();
+    fs::write(dir.path().join("data/disposable.ron"), "(count: ").unwrap();
+
+    let readers: Vec<_> = (0..8)
+        .map(|_| {
+            let storage = storage.clone();
+            std::thread::spawn(move || {
+                let mut handle = StoreHandle::<Disposable>::new("disposable");
+                storage.read(&mut handle).map(|()| handle.get_store().count)
+            })
+        })
+        .collect();
+
+    for reader in readers {
+        assert_eq!(reader.join().unwrap().unwrap(), 0);
+    }
+    let entries = common::entries(&dir.path().join("data"));
+    assert_eq!(entries.len(), 2, "{:?}", entries);
+    assert_eq!(
+        fs::read_to_string(dir.path().join("data").join(&entries[1])).unwrap(),
+        "(count: "
+    );
+}
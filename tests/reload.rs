@@ -0,0 +1,75 @@
+mod common;
+
+use std::fs;
+use std::time::Duration;
+
+use rusty_store::{StoreManager, Storing};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default, Debug, Storing)]
+pub struct Settings {
+    pub theme: String,
+}
+
+#[test]
+fn reloads_only_external_changes() {
+    let (_dir, storage) = common::storage();
+    let mut manager = StoreManager::<Settings>::new(&storage, "settings").unwrap();
+    let mut other = StoreManager::<Settings>::new(&storage, "settings").unwrap();
+
+    manager
+        .modify_store(|store| store.theme = "dark".to_owned())
+        .unwrap();
+    assert!(!manager.reload_if_changed().unwrap());
+
+    other.get_store_alive().unwrap();
+    other
+        .modify_store(|store| store.theme = "light".to_owned())
+        .unwrap();
+
+    assert!(manager.reload_if_changed().unwrap());
+    assert_eq!(manager.get_store().theme, "light");
+    assert!(!manager.reload_if_changed().unwrap());
+
+    // The reloaded version is the one the next save is checked against.
+    manager
+        .modify_store(|store| store.theme = "blue".to_owned())
+        .unwrap();
+}
+
+#[test]
+fn keeps_last_good_store_on_parse_failure() {
+    let (dir, storage) = common::storage();
+    let mut manager = StoreManager::<Settings>::new(&storage, "settings").unwrap();
+    manager
+        .modify_store(|store| store.theme = "dark".to_owned())
+        .unwrap();
+
+    let path = dir.path().join("data/settings.ron");
+    fs::write(&path, "(theme: ").unwrap();
+
+    assert!(manager.reload_if_changed().is_err());
+    assert_eq!(manager.get_store().theme, "dark");
+    assert_eq!(fs::read_to_string(&path).unwrap(), "(theme: ");
+
+    fs::write(&path, "(theme: \"light\")").unwrap();
+    assert!(manager.reload_if_changed().unwrap());
+    assert_eq!(manager.get_store().theme, "light");
+}
+
+#[test]
+fn ignores_external_changes_while_autosave_is_pending() {
+    let (dir, storage) = common::storage();
+    let mut manager = StoreManager::<Settings>::new(&storage, "settings").unwrap();
+    manager.enable_autosave(Duration::from_secs(60));
+
+    manager
+        .modify_store(|store| store.theme = "dark".to_owned())
+        .unwrap();
+    fs::write(dir.path().join("data/settings.ron"), "(theme: \"light\")").unwrap();
+
+    assert!(!manager.reload_if_changed().unwrap());
+    assert_eq!(manager.get_store().theme, "dark");
+
+    manager.disable_autosave().unwrap_err();
+}
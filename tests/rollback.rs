@@ -0,0 +1,210 @@
+mod common;
+
+use std::fs;
+
+use rusty_store::{StoreError, StoreManager, Storing};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Storing)]
+pub struct Settings {
+    pub name: String,
+    pub values: Vec<u32>,
+}
+
+/// A store that cannot be serialized while `value` is `UNWRITABLE`.
+#[derive(Deserialize, Default, Debug, PartialEq, Storing)]
+pub struct Picky {
+    pub value: u32,
+}
+
+const UNWRITABLE: u32 = 13;
+
+impl Serialize for Picky {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::{Error, SerializeStruct};
+
+        if self.value == UNWRITABLE {
+            return Err(S::Error::custom("unwritable value"));
+        }
+        let mut state = serializer.serialize_struct("Picky", 1)?;
+        state.serialize_field("value", &self.value)?;
+        state.end()
+    }
+}
+
+/// A store that cannot be deserialized while `value` is `UNREADABLE`.
+#[derive(Serialize, Default, Debug, PartialEq, Storing)]
+pub struct Stubborn {
+    pub value: u32,
+}
+
+const UNREADABLE: u32 = 7;
+
+impl<'de> Deserialize<'de> for Stubborn {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        #[derive(Deserialize)]
+        struct Raw {
+            value: u32,
+        }
+
+        let raw = Raw::deserialize(deserializer)?;
+        if raw.value == UNREADABLE {
+            return Err(D::Error::custom("unreadable value"));
+        }
+        Ok(Self { value: raw.value })
+    }
+}
+
+#[test]
+fn failed_serialization_rolls_back_modification() {
+    let (_dir, storage) = common::storage();
+    let mut manager = StoreManager::<Picky>::new(&storage, "picky").unwrap();
+    manager.modify_store(|store| store.value = 1).unwrap();
+
+    let result = manager.modify_store(|store| store.value = UNWRITABLE);
+
+    assert!(matches!(result, Err(StoreError::Ron(_))));
+    assert_eq!(manager.get_store().value, 1);
+    assert_eq!(manager.get_store_alive().unwrap().value, 1);
+}
+
+#[test]
+fn failed_write_rolls_back_modification() {
+    let (dir, storage) = common::storage();
+    let mut manager = StoreManager::<Settings>::new(&storage, "settings").unwrap();
+    manager
+        .modify_store(|store| store.name = "before".to_owned())
+        .unwrap();
+
+    // Replacing the store file with a directory makes every write fail.
+    let path = dir.path().join("data/settings.ron");
+    fs::remove_file(&path).unwrap();
+    fs::create_dir(&path).unwrap();
+
+    let result = manager.modify_store(|store| {
+        store.name = "after".to_owned();
+        store.values.push(1);
+    });
+
+    assert!(result.is_err());
+    assert_eq!(
+        manager.get_store(),
+        &Settings {
+            name: "before".to_owned(),
+            values: Vec::new(),
+        }
+    );
+}
+
+#[test]
+fn conflict_rolls_back_modification() {
+    let (dir, storage) = common::storage();
+    let mut manager = StoreManager::<Settings>::new(&storage, "settings").unwrap();
+
+    fs::write(
+        dir.path().join("data/settings.ron"),
+        "(name: \"external\", values: [])",
+    )
+    .unwrap();
+
+    let result = manager.modify_store(|store| store.values.push(1));
+
+    assert!(matches!(result, Err(StoreError::Conflict(_))));
+    assert!(manager.get_store().values.is_empty());
+    assert_eq!(manager.get_store_alive().unwrap().name, "external");
+}
+
+#[test]
+fn successful_write_keeps_modification() {
+    let (_dir, storage) = common::storage();
+    let mut manager = StoreManager::<Settings>::new(&storage, "settings").unwrap();
+
+    manager.modify_store(|store| store.values.push(1)).unwrap();
+
+    assert_eq!(manager.get_store().values, vec![1]);
+    assert_eq!(manager.get_store_alive().unwrap().values, vec![1]);
+}
+
+#[test]
+fn failed_rollback_is_reported() {
+    let (dir, storage) = common::storage();
+    let mut manager = StoreManager::<Stubborn>::new(&storage, "stubborn").unwrap();
+    manager.get_store_mut().value = UNREADABLE;
+
+    let path = dir.path().join("data/stubborn.ron");
+    fs::remove_file(&path).unwrap();
+    fs::create_dir(&path).unwrap();
+
+    let result = manager.modify_store(|store| store.value = 1);
+
+    assert!(
+        matches!(result, Err(StoreError::Rollback { .. })),
+        "{:?}",
+        result
+    );
+}
+
+#[cfg(feature = "json")]
+mod json {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    use rusty_store::Format;
+
+    /// A store which RON cannot read back, as flattened fields are only known to self-describing
+    /// formats as map entries.
+    #[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+    pub struct Flattened {
+        #[serde(flatten)]
+        pub extra: BTreeMap<String, u32>,
+    }
+
+    impl Storing for Flattened {
+        fn format() -> Format {
+            Format::Json
+        }
+    }
+
+    #[test]
+    fn rolls_back_in_the_store_format() {
+        let (dir, storage) = common::storage();
+        let mut manager = StoreManager::<Flattened>::new(&storage, "flattened").unwrap();
+        manager
+            .modify_store(|store| {
+                store.extra.insert("count".to_owned(), 1);
+            })
+            .unwrap();
+
+        let path = dir.path().join("data/flattened.json");
+        fs::remove_file(&path).unwrap();
+        fs::create_dir(&path).unwrap();
+
+        let result = manager.modify_store(|store| {
+            store.extra.insert("count".to_owned(), 2);
+        });
+
+        assert!(result.is_err());
+        assert!(!matches!(result, Err(StoreError::Rollback { .. })));
+        assert_eq!(manager.get_store().extra["count"], 1);
+    }
+
+    #[test]
+    fn undoes_in_the_store_format() {
+        let (_dir, storage) = common::storage();
+        let mut manager = StoreManager::<Flattened>::new(&storage, "flattened").unwrap();
+        manager.enable_history(10);
+
+        for count in 1..=2 {
+            manager
+                .modify_store(|store| {
+                    store.extra.insert("count".to_owned(), count);
+                })
+                .unwrap();
+        }
+
+        assert!(manager.undo().unwrap());
+        assert_eq!(manager.get_store().extra["count"], 1);
+    }
+}
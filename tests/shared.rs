@@ -0,0 +1,143 @@
+mod common;
+
+use std::sync::{Arc, Barrier};
+use std::thread;
+
+use rusty_store::{ModifyError, SharedStoreManager, StoreManager, Storing};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default, Debug, Storing)]
+pub struct Counter {
+    pub count: u32,
+    pub log: Vec<u32>,
+}
+
+const THREADS: u32 = 8;
+const INCREMENTS: u32 = 25;
+
+#[test]
+fn concurrent_modifications_are_serialized() {
+    let (_dir, storage) = common::storage();
+    let manager = SharedStoreManager::<Counter>::new(&storage, "counter").unwrap();
+    let barrier = Arc::new(Barrier::new(THREADS as usize * 2));
+
+    let writers = (0..THREADS).map(|_| {
+        let manager = manager.clone();
+        let barrier = barrier.clone();
+        thread::spawn(move || {
+            barrier.wait();
+            for _ in 0..INCREMENTS {
+                manager
+                    .modify_store(|store| {
+                        store.count += 1;
+                        store.log.push(store.count);
+                    })
+                    .unwrap();
+            }
+        })
+    });
+    let readers = (0..THREADS).map(|_| {
+        let manager = manager.clone();
+        let barrier = barrier.clone();
+        thread::spawn(move || {
+            barrier.wait();
+            for _ in 0..INCREMENTS {
+                let store = manager.read();
+                // A reader never sees a modification halfway through.
+                assert_eq!(store.count as usize, store.log.len());
+            }
+        })
+    });
+
+    let handles: Vec<_> = writers.chain(readers).collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    let expected = THREADS * INCREMENTS;
+    assert_eq!(manager.read().count, expected);
+    assert_eq!(manager.read().log, (1..=expected).collect::<Vec<_>>());
+
+    let reloaded = StoreManager::<Counter>::new(&storage, "counter").unwrap();
+    assert_eq!(reloaded.get_store().count, expected);
+}
+
+#[test]
+fn clones_share_the_store() {
+    let (_dir, storage) = common::storage();
+    let manager = SharedStoreManager::<Counter>::new(&storage, "counter").unwrap();
+    let clone = manager.clone();
+
+    clone.modify_store(|store| store.count = 3).unwrap();
+
+    assert_eq!(manager.read().count, 3);
+}
+
+#[test]
+fn rejected_modification_is_discarded() {
+    let (_dir, storage) = common::storage();
+    let manager = SharedStoreManager::<Counter>::new(&storage, "counter").unwrap();
+
+    let result = manager.try_modify_store(|store| {
+        store.count = 10;
+        Err::<(), _>("rejected")
+    });
+
+    assert!(matches!(result, Err(ModifyError::Rejected("rejected"))));
+    assert_eq!(manager.read().count, 0);
+}
+
+#[test]
+fn reloads_external_changes() {
+    let (_dir, storage) = common::storage();
+    let manager = SharedStoreManager::<Counter>::new(&storage, "counter").unwrap();
+    let mut other = StoreManager::<Counter>::new(&storage, "counter").unwrap();
+
+    other.modify_store(|store| store.count = 4).unwrap();
+
+    assert_eq!(manager.read().count, 0);
+    assert_eq!(manager.get_store_alive().unwrap().count, 4);
+    assert_eq!(manager.read().count, 4);
+}
+
+#[test]
+fn survives_a_panicking_thread() {
+    let (_dir, storage) = common::storage();
+    let manager = SharedStoreManager::<Counter>::new(&storage, "counter").unwrap();
+
+    let panicking = manager.clone();
+    let result = thread::spawn(move || {
+        let _manager = panicking.lock();
+        panic!("poisoning the lock");
+    })
+    .join();
+    assert!(result.is_err());
+
+    manager.modify_store(|store| store.count = 1).unwrap();
+    assert_eq!(manager.read().count, 1);
+}
+
+#[test]
+fn panicking_modifications_are_rolled_back() {
+    let (_dir, storage) = common::storage();
+    let manager = SharedStoreManager::<Counter>::new(&storage, "counter").unwrap();
+    manager.modify_store(|store| store.count = 1).unwrap();
+
+    let panicking = manager.clone();
+    let result = thread::spawn(move || {
+        panicking
+            .modify_store(|store| {
+                store.count = 2;
+                store.log.push(2);
+                panic!("half-done modification");
+            })
+            .unwrap();
+    })
+    .join();
+    assert!(result.is_err());
+
+    assert_eq!(manager.read().count, 1);
+    assert!(manager.read().log.is_empty());
+    let reloaded = StoreManager::<Counter>::new(&storage, "counter").unwrap();
+    assert_eq!(reloaded.get_store().count, 1);
+}
@@ -0,0 +1,79 @@
+mod common;
+
+use std::fs;
+
+use rusty_store::{ModifyError, StoreError, StoreManager, Storing};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Storing)]
+pub struct Server {
+    pub host: String,
+    pub port: u32,
+}
+
+fn set_port(store: &mut Server, port: u32) -> Result<u32, String> {
+    store.host = "localhost".to_owned();
+    store.port = port;
+    if port > u16::MAX as u32 {
+        return Err(format!("port {} is out of range", port));
+    }
+    Ok(store.port)
+}
+
+#[test]
+fn accepted_change_is_saved_and_returned() {
+    let (_dir, storage) = common::storage();
+    let mut manager = StoreManager::<Server>::new(&storage, "server").unwrap();
+
+    let port = manager
+        .try_modify_store(|store| set_port(store, 8080))
+        .unwrap();
+
+    assert_eq!(port, 8080);
+    assert_eq!(manager.get_store_alive().unwrap().port, 8080);
+}
+
+#[test]
+fn rejected_change_is_discarded_without_writing() {
+    let (dir, storage) = common::storage();
+    let mut manager = StoreManager::<Server>::new(&storage, "server").unwrap();
+    manager
+        .try_modify_store(|store| set_port(store, 8080))
+        .unwrap();
+    let before = fs::read_to_string(dir.path().join("data/server.ron")).unwrap();
+
+    let result = manager.try_modify_store(|store| set_port(store, 70000));
+
+    assert!(matches!(result, Err(ModifyError::Rejected(reason)) if reason.contains("70000")));
+    assert_eq!(
+        manager.get_store(),
+        &Server {
+            host: "localhost".to_owned(),
+            port: 8080,
+        }
+    );
+    assert_eq!(
+        fs::read_to_string(dir.path().join("data/server.ron")).unwrap(),
+        before
+    );
+}
+
+#[test]
+fn store_errors_are_wrapped_and_rolled_back() {
+    let (dir, storage) = common::storage();
+    let mut manager = StoreManager::<Server>::new(&storage, "server").unwrap();
+
+    fs::write(
+        dir.path().join("data/server.ron"),
+        "(host: \"external\", port: 1)",
+    )
+    .unwrap();
+
+    let result = manager.try_modify_store(|store| set_port(store, 8080));
+
+    assert!(matches!(
+        result,
+        Err(ModifyError::Store(StoreError::Conflict(_)))
+    ));
+    assert_eq!(manager.get_store(), &Server::default());
+}
@@ -0,0 +1,139 @@
+mod common;
+
+use std::fs;
+
+use rusty_store::{StoreError, StoreManager, Storing, StoringType, ValidationError};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Server {
+    pub host: String,
+    pub port: u16,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Config {
+    pub server: Server,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            server: Server {
+                host: "localhost".to_owned(),
+                port: 8080,
+            },
+        }
+    }
+}
+
+impl Storing for Config {
+    fn store_type() -> StoringType {
+        StoringType::Config
+    }
+
+    fn validate(&self) -> Result<(), ValidationError> {
+        if self.server.port < 1024 {
+            return Err(ValidationError::new(
+                "server.port",
+                format!("{} is a privileged port", self.server.port),
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn assert_invalid_port(result: Result<(), StoreError>, port: u16) {
+    match result {
+        Err(StoreError::Validation { path, message }) => {
+            assert_eq!(path, "server.port");
+            assert_eq!(message, format!("{} is a privileged port", port));
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn invalid_files_are_rejected_on_read() {
+    let (dir, storage) = common::storage();
+    fs::create_dir_all(dir.path().join("config")).unwrap();
+    let path = dir.path().join("config/config.ron");
+    let contents = "(server: (host: \"localhost\", port: 80))";
+    fs::write(&path, contents).unwrap();
+
+    let result = StoreManager::<Config>::new(&storage, "config");
+
+    assert_invalid_port(result.map(|_| ()), 80);
+    assert_eq!(fs::read_to_string(&path).unwrap(), contents);
+}
+
+#[test]
+fn fresh_stores_are_created_from_the_default() {
+    let (dir, storage) = common::storage();
+
+    let manager = StoreManager::<Config>::new(&storage, "config").unwrap();
+
+    assert_eq!(*manager.get_store(), Config::default());
+    assert!(dir.path().join("config/config.ron").exists());
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+pub struct Unset {
+    pub port: u16,
+}
+
+impl Storing for Unset {
+    fn validate(&self) -> Result<(), ValidationError> {
+        if self.port == 0 {
+            return Err(ValidationError::new("port", "must be set"));
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn invalid_defaults_are_not_written() {
+    let (dir, storage) = common::storage();
+
+    let result = StoreManager::<Unset>::new(&storage, "unset");
+
+    assert!(matches!(result, Err(StoreError::Validation { .. })));
+    assert!(!dir.path().join("data/unset.ron").exists());
+}
+
+#[test]
+fn invalid_modifications_are_not_written() {
+    let (dir, storage) = common::storage();
+    let mut manager = StoreManager::<Config>::new(&storage, "config").unwrap();
+    let path = dir.path().join("config/config.ron");
+    let before = fs::read_to_string(&path).unwrap();
+
+    let result = manager.modify_store(|store| store.server.port = 22);
+
+    assert_invalid_port(result, 22);
+    assert_eq!(manager.get_store().server.port, 8080);
+    assert_eq!(fs::read_to_string(&path).unwrap(), before);
+}
+
+#[test]
+fn valid_stores_round_trip() {
+    let (_dir, storage) = common::storage();
+    let mut manager = StoreManager::<Config>::new(&storage, "config").unwrap();
+
+    manager
+        .modify_store(|store| store.server.port = 4000)
+        .unwrap();
+
+    let reloaded = StoreManager::<Config>::new(&storage, "config").unwrap();
+    assert_eq!(reloaded.get_store().server.port, 4000);
+}
+
+#[test]
+fn validation_errors_name_the_field() {
+    let err = StoreError::from(ValidationError::new("server.port", "must not be 0"));
+
+    assert_eq!(
+        err.to_string(),
+        "Invalid store value at server.port: must not be 0"
+    );
+}
@@ -0,0 +1,106 @@
+#![cfg(feature = "watcher")]
+
+mod common;
+
+use std::fs;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rusty_store::{SharedStoreManager, StoreManager, Storing};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default, Debug, Storing)]
+pub struct Settings {
+    pub theme: String,
+}
+
+type Changes = Arc<Mutex<Vec<String>>>;
+
+fn observe(manager: &SharedStoreManager<Settings>) -> Changes {
+    let changes = Changes::default();
+    {
+        let changes = changes.clone();
+        manager
+            .lock()
+            .subscribe(move |_, new| changes.lock().unwrap().push(new.theme.clone()));
+    }
+    changes
+}
+
+fn wait_for(condition: impl Fn() -> bool) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while Instant::now() < deadline {
+        if condition() {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    condition()
+}
+
+#[test]
+fn reloads_external_changes() {
+    let (_dir, storage) = common::storage();
+    let manager = SharedStoreManager::<Settings>::new(&storage, "settings").unwrap();
+    let changes = observe(&manager);
+    let _watcher = manager.watch().unwrap();
+
+    let mut other = StoreManager::<Settings>::new(&storage, "settings").unwrap();
+    other
+        .modify_store(|store| store.theme = "dark".to_owned())
+        .unwrap();
+
+    assert!(wait_for(|| manager.read().theme == "dark"));
+    assert_eq!(*changes.lock().unwrap(), vec!["dark".to_owned()]);
+}
+
+#[test]
+fn ignores_own_writes() {
+    let (_dir, storage) = common::storage();
+    let manager = SharedStoreManager::<Settings>::new(&storage, "settings").unwrap();
+    let changes = observe(&manager);
+    let _watcher = manager.watch().unwrap();
+
+    manager
+        .modify_store(|store| store.theme = "dark".to_owned())
+        .unwrap();
+    manager
+        .lock()
+        .modify_store_uncommitted(|store| store.theme = "unsaved".to_owned());
+
+    thread::sleep(Duration::from_millis(300));
+    assert_eq!(manager.read().theme, "unsaved");
+    assert_eq!(*changes.lock().unwrap(), vec!["dark".to_owned()]);
+}
+
+#[test]
+fn keeps_last_good_state_on_parse_failure() {
+    let (dir, storage) = common::storage();
+    let manager = SharedStoreManager::<Settings>::new(&storage, "settings").unwrap();
+    manager
+        .modify_store(|store| store.theme = "dark".to_owned())
+        .unwrap();
+    let changes = observe(&manager);
+    let _watcher = manager.watch().unwrap();
+
+    let path = dir.path().join("data/settings.ron");
+    fs::write(&path, "(theme: ").unwrap();
+    thread::sleep(Duration::from_millis(300));
+    assert_eq!(manager.read().theme, "dark");
+
+    fs::write(&path, "(theme: \"light\")").unwrap();
+    assert!(wait_for(|| manager.read().theme == "light"));
+    assert_eq!(*changes.lock().unwrap(), vec!["light".to_owned()]);
+}
+
+#[test]
+fn stops_watching_when_dropped() {
+    let (dir, storage) = common::storage();
+    let manager = SharedStoreManager::<Settings>::new(&storage, "settings").unwrap();
+    drop(manager.watch().unwrap());
+
+    fs::write(dir.path().join("data/settings.ron"), "(theme: \"light\")").unwrap();
+    thread::sleep(Duration::from_millis(300));
+    assert_eq!(manager.read().theme, "");
+}